@@ -0,0 +1,64 @@
+/*
+ * C interface to the shredder garbage collector (enabled with the `ffi` feature).
+ * These declarations mirror `src/ffi.rs` (see there for the full documentation), and
+ * `tests/ffi.rs` checks them against it with a C compiler.
+ */
+
+#ifndef SHREDDER_H
+#define SHREDDER_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* An opaque, owning handle to a garbage collected object. Each live handle is a root. */
+typedef struct ShredderHandle ShredderHandle;
+
+/* A destructor for a foreign object, called with its `data` pointer */
+typedef void (*ShredderDestroyFn)(void *data);
+
+/* Make a new handle to the same object. Returns NULL if `handle` is NULL. */
+ShredderHandle *shredder_handle_clone(const ShredderHandle *handle);
+
+/* Release a handle. Does nothing if `handle` is NULL. */
+void shredder_handle_drop(ShredderHandle *handle);
+
+/* The id of the object `handle` points at. Equal ids mean the same object. */
+uint64_t shredder_handle_id(const ShredderHandle *handle);
+
+/* Run a collection on the calling thread */
+void shredder_collect(void);
+
+/* Wait for the destructors of all garbage found so far to run */
+void shredder_synchronize_destructors(void);
+
+/* How many objects the collector is tracking */
+size_t shredder_number_of_tracked_allocations(void);
+
+/* How many handles (on both sides of the boundary) currently exist */
+size_t shredder_number_of_active_handles(void);
+
+/*
+ * Hand a host object over to the collector, returning a handle that roots it. Once the object is
+ * garbage, `destroy` (if not NULL) is called with `data`, possibly on another thread.
+ */
+ShredderHandle *shredder_register_foreign(void *data, ShredderDestroyFn destroy);
+
+/* The `data` a foreign object was registered with, or NULL if `handle` isn't a foreign object */
+void *shredder_foreign_data(const ShredderHandle *handle);
+
+/*
+ * Make the foreign object `owner` keep `target` alive (without rooting it).
+ * Returns false if `owner` isn't a foreign object.
+ */
+bool shredder_foreign_add_reference(const ShredderHandle *owner, const ShredderHandle *target);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SHREDDER_H */
@@ -0,0 +1,117 @@
+use std::cell::UnsafeCell;
+use std::fmt::{self, Debug, Formatter};
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll, Waker};
+
+use parking_lot::Mutex;
+
+use crate::{GcSafe, Scan, Scanner};
+
+/// A mutex for use inside a `Gc`, which is locked asynchronously with `Gc::lock_async`.
+///
+/// Unlike `Gc<Mutex<T>>::lock`, waiting for the lock never blocks the thread. Instead the future
+/// registers a waker, and stops holding onto the `Gc` data while it waits (so the collector can
+/// still scan it). A `std` `Mutex` can be unlocked without telling anyone, so there's no async way
+/// to lock one; use this instead when locking from async code.
+pub struct GcAsyncMutex<T> {
+    locked: AtomicBool,
+    /// wakers for the futures waiting on this lock
+    waiting: Mutex<Vec<Waker>>,
+    data: UnsafeCell<T>,
+}
+
+// Safety: Access to `data` is synchronized by `locked`, just like in a `Mutex`
+unsafe impl<T: Send> Send for GcAsyncMutex<T> {}
+unsafe impl<T: Send> Sync for GcAsyncMutex<T> {}
+
+impl<T> GcAsyncMutex<T> {
+    /// Create a new, unlocked, `GcAsyncMutex`
+    pub fn new(v: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            waiting: Mutex::new(Vec::new()),
+            data: UnsafeCell::new(v),
+        }
+    }
+
+    /// Consume the mutex, returning the data inside
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+
+    /// Get mutable access to the data inside. (No locking is needed, since we have `&mut self`.)
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    pub(crate) fn try_acquire(&self) -> bool {
+        self.locked
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    }
+
+    pub(crate) fn poll_acquire(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.try_acquire() {
+            return Poll::Ready(());
+        }
+
+        // `release` takes this lock before unlocking, so we can't miss the wakeup
+        let mut waiting = self.waiting.lock();
+        if self.try_acquire() {
+            return Poll::Ready(());
+        }
+
+        if !waiting.iter().any(|w| w.will_wake(cx.waker())) {
+            waiting.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+
+    /// Safety: The caller must have acquired the lock, and not be using the data anymore
+    pub(crate) unsafe fn release(&self) {
+        let waiting = {
+            let mut waiting = self.waiting.lock();
+            self.locked.store(false, Ordering::SeqCst);
+            mem::take(&mut *waiting)
+        };
+
+        // Everyone gets a chance to retry, since some of these futures may have been dropped
+        for waker in waiting {
+            waker.wake();
+        }
+    }
+
+    /// Safety: The caller must have acquired the lock
+    pub(crate) unsafe fn data_ptr(&self) -> *mut T {
+        self.data.get()
+    }
+}
+
+impl<T: Default> Default for GcAsyncMutex<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> Debug for GcAsyncMutex<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcAsyncMutex")
+            .field("locked", &self.locked.load(Ordering::SeqCst))
+            .finish_non_exhaustive()
+    }
+}
+
+unsafe impl<T: Scan> Scan for GcAsyncMutex<T> {
+    #[inline]
+    fn scan(&self, scanner: &mut Scanner<'_>) {
+        // A locked guard holds a warrant for the data, so this lock should always be free here
+        if self.try_acquire() {
+            scanner.scan(unsafe { &*self.data_ptr() });
+            unsafe { self.release() };
+        } else {
+            error!("A GcAsyncMutex was in use when it was scanned -- something is buggy here! (no memory unsafety yet, so proceeding...)");
+        }
+    }
+}
+unsafe impl<T: GcSafe> GcSafe for GcAsyncMutex<T> {}
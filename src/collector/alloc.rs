@@ -1,18 +1,67 @@
-use std::alloc::{alloc, dealloc, Layout};
+use std::alloc::{alloc, dealloc, handle_alloc_error, Layout};
+use std::any::{type_name, TypeId};
 use std::mem::{self, ManuallyDrop};
 use std::panic::UnwindSafe;
-use std::ptr;
+use std::ptr::{self, NonNull};
 
 use crate::collector::InternalGcRef;
-use crate::{Finalize, Scan, Scanner};
+use crate::{Finalize, GcSafe, Scan, Scanner};
 
 /// Represents a piece of data allocated by shredder
 #[derive(Copy, Clone, Debug, Hash)]
 pub struct GcAllocation {
     scan_ptr: *const dyn Scan,
+    /// the layout of the whole allocation (which may be larger than what `scan_ptr` points to)
+    layout: Layout,
+    /// the name of the type that was allocated, for diagnostics
+    type_name: &'static str,
+    /// the type that was allocated, if it's known to be `'static` (used to enumerate allocations)
+    type_id: Option<TypeId>,
+    /// the layout of the `Box`'s own buffer, if the allocation just holds a `Box` that owns the data
+    /// (see `holding_box`)
+    boxed: Option<Layout>,
     deallocation_action: DeallocationAction,
 }
 
+/// Sits at the start of a slice allocation, right before the elements
+/// This gives us something `Sized` to point our `scan_ptr` at
+struct SliceHeader<T> {
+    len: usize,
+    /// points just past this header, into the same allocation
+    elements: *mut T,
+}
+
+impl<T> SliceHeader<T> {
+    /// The layout of a header followed by `len` elements, and the offset of those elements
+    fn layout(len: usize) -> (Layout, usize) {
+        let elements_layout = Layout::array::<T>(len).expect("slice too large to allocate");
+        let (layout, offset) = Layout::new::<Self>()
+            .extend(elements_layout)
+            .expect("slice too large to allocate");
+        (layout.pad_to_align(), offset)
+    }
+
+    fn elements(&self) -> *mut [T] {
+        ptr::slice_from_raw_parts_mut(self.elements, self.len)
+    }
+}
+
+impl<T> Drop for SliceHeader<T> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.elements()) };
+    }
+}
+
+// Safety: The header is just a view onto the elements, so it inherits their properties
+unsafe impl<T: GcSafe> GcSafe for SliceHeader<T> {}
+unsafe impl<T: Scan> Scan for SliceHeader<T> {
+    #[inline]
+    fn scan(&self, scanner: &mut Scanner<'_>) {
+        let elements: &[T] = unsafe { &*self.elements() };
+        scanner.scan(elements);
+    }
+}
+
 /// What additional action should we run before deallocating?
 #[derive(Copy, Clone, Debug, Hash)]
 pub enum DeallocationAction {
@@ -31,21 +80,99 @@ unsafe impl Sync for GcAllocation {}
 
 impl GcAllocation {
     pub fn allocate_with_drop<T: Scan + 'static>(v: T) -> (Self, *const T) {
+        // Safety: `T: 'static`, so it's fine to drop this data whenever
+        let (mut allocation, raw_ptr) = unsafe { Self::allocate_with_unchecked_drop(v) };
+        allocation.type_id = Some(TypeId::of::<T>());
+        (allocation, raw_ptr)
+    }
+
+    /// Safety: The data must be dropped before `T`'s lifetime ends
+    pub unsafe fn allocate_with_unchecked_drop<T: Scan>(v: T) -> (Self, *const T) {
         let (scan_ptr, raw_ptr) = Self::raw_allocate(v);
         (
             Self {
                 scan_ptr,
+                layout: Layout::new::<T>(),
+                type_name: type_name::<T>(),
+                type_id: None,
+                boxed: None,
                 deallocation_action: DeallocationAction::RunDrop,
             },
             raw_ptr,
         )
     }
 
+    /// Allocate the contents of `v` inline, in a single allocation (running drop on deallocation)
+    pub fn allocate_slice_with_drop<T: Scan + 'static>(mut v: Vec<T>) -> (Self, *const [T]) {
+        let len = v.len();
+        let (layout, offset) = SliceHeader::<T>::layout(len);
+
+        let (header_ptr, elements_ptr) = unsafe {
+            let heap_space = alloc(layout);
+            if heap_space.is_null() {
+                handle_alloc_error(layout);
+            }
+
+            // `layout` is aligned for both the header and the elements
+            #[allow(clippy::cast_ptr_alignment)]
+            let header_ptr = heap_space.cast::<SliceHeader<T>>();
+            #[allow(clippy::cast_ptr_alignment)]
+            let elements_ptr = heap_space.add(offset).cast::<T>();
+            ptr::write(
+                header_ptr,
+                SliceHeader {
+                    len,
+                    elements: elements_ptr,
+                },
+            );
+
+            // Move the elements out of the `Vec`, which now just needs to free its buffer
+            ptr::copy_nonoverlapping(v.as_ptr(), elements_ptr, len);
+            v.set_len(0);
+
+            (header_ptr.cast_const(), elements_ptr.cast_const())
+        };
+
+        (
+            Self {
+                scan_ptr: header_ptr,
+                layout,
+                type_name: type_name::<[T]>(),
+                type_id: Some(TypeId::of::<[T]>()),
+                boxed: None,
+                deallocation_action: DeallocationAction::RunDrop,
+            },
+            ptr::slice_from_raw_parts(elements_ptr, len),
+        )
+    }
+
+    /// Allocate space for a `T` (running drop on deallocation), but don't write anything there
+    pub fn allocate_uninit_with_drop<T: Scan + 'static>() -> (Self, *const T) {
+        let layout = Layout::new::<T>();
+        let data_ptr = Self::allocate_space::<T>().cast_const();
+
+        (
+            Self {
+                scan_ptr: data_ptr,
+                layout,
+                type_name: type_name::<T>(),
+                type_id: Some(TypeId::of::<T>()),
+                boxed: None,
+                deallocation_action: DeallocationAction::RunDrop,
+            },
+            data_ptr,
+        )
+    }
+
     pub fn allocate_no_drop<T: Scan>(v: T) -> (Self, *const T) {
         let (scan_ptr, raw_ptr) = Self::raw_allocate(v);
         (
             Self {
                 scan_ptr,
+                layout: Layout::new::<T>(),
+                type_name: type_name::<T>(),
+                type_id: None,
+                boxed: None,
                 deallocation_action: DeallocationAction::DoNothing,
             },
             raw_ptr,
@@ -61,6 +188,10 @@ impl GcAllocation {
         (
             Self {
                 scan_ptr,
+                layout: Layout::new::<T>(),
+                type_name: type_name::<T>(),
+                type_id: None,
+                boxed: None,
                 deallocation_action: DeallocationAction::RunFinalizer { finalize_ptr },
             },
             raw_ptr,
@@ -71,7 +202,7 @@ impl GcAllocation {
     fn raw_allocate<'a, T: Scan + 'a>(v: T) -> (*const dyn Scan, *const T) {
         // This is a straightforward use of alloc/write -- it should be undef free
         let data_ptr = unsafe {
-            let heap_space = alloc(Layout::new::<T>()) as *mut T;
+            let heap_space = Self::allocate_space::<T>();
             ptr::write(heap_space, v);
             // NOTE: Write moves the data into the heap
 
@@ -86,6 +217,23 @@ impl GcAllocation {
         (fat_ptr, data_ptr)
     }
 
+    /// Space for a `T` on the heap. Zero sized types don't need any, so they get a dangling pointer
+    fn allocate_space<T>() -> *mut T {
+        let layout = Layout::new::<T>();
+        if layout.size() == 0 {
+            return NonNull::dangling().as_ptr();
+        }
+
+        unsafe {
+            let heap_space = alloc(layout);
+            if heap_space.is_null() {
+                handle_alloc_error(layout);
+            }
+            #[allow(clippy::cast_ptr_alignment)]
+            heap_space.cast::<T>()
+        }
+    }
+
     // This is unsafe, since we must externally guarantee that no-one still holds a pointer to the data
     // (Luckily this is the point of the garbage collector!)
     pub unsafe fn deallocate(self) {
@@ -94,10 +242,7 @@ impl GcAllocation {
         match self.deallocation_action {
             DeallocationAction::DoNothing => {
                 // The name here is a bit of a lie, because we still need to invalidate handles
-                let mut scanner = Scanner::new(|h| {
-                    h.invalidate();
-                });
-                (&*scan_ptr).scan(&mut scanner);
+                self.invalidate_handles();
             }
             DeallocationAction::RunDrop => {
                 // Safe type shift: the contract of this method is that the scan_ptr doesn't alias
@@ -109,12 +254,7 @@ impl GcAllocation {
             DeallocationAction::RunFinalizer { finalize_ptr } => {
                 // First of all invalidate handles, just in case of a bad `Finalize` implementation
                 // (If it doesn't delegate correctly, `Gc`s could be left dangling)
-                {
-                    let mut scanner = Scanner::new(|h| {
-                        h.invalidate();
-                    });
-                    (&*scan_ptr).scan(&mut scanner);
-                }
+                self.invalidate_handles();
 
                 // We know this method can only be called if `scan_ptr` doesn't alias
                 // And we know `finalize_ptr` ~= `scan_ptr`
@@ -123,9 +263,96 @@ impl GcAllocation {
             }
         }
 
-        let dealloc_layout = Layout::for_value(&*scan_ptr);
-        let heap_ptr = scan_ptr as *mut u8;
-        dealloc(heap_ptr, dealloc_layout);
+        self.free();
+    }
+
+    // Like `deallocate`, but never runs `drop` or `finalize` (which leaks anything the data owns)
+    // This has the same safety requirements as `deallocate`
+    pub unsafe fn deallocate_without_drop(self) {
+        self.invalidate_handles();
+        self.free();
+    }
+
+    unsafe fn invalidate_handles(&self) {
+        let mut scanner = Scanner::new(|h| {
+            h.invalidate();
+        });
+        (&*self.scan_ptr).scan(&mut scanner);
+    }
+
+    /// Free the allocation, without doing anything to the data inside
+    pub unsafe fn free(self) {
+        // Zero sized data never got any space from the allocator (see `allocate_space`)
+        if self.layout.size() != 0 {
+            let heap_ptr = self.scan_ptr as *mut u8;
+            dealloc(heap_ptr, self.layout);
+        }
+    }
+
+    /// How many bytes this allocation takes up on the heap (including the data a `Box` in it owns)
+    pub fn size(&self) -> usize {
+        self.layout.size() + self.boxed.map_or(0, |boxed| boxed.size())
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// The type the data was allocated as, if it's known (it isn't for data that may not be
+    /// `'static`)
+    pub fn type_id(&self) -> Option<TypeId> {
+        self.type_id
+    }
+
+    /// Report `T` as the allocated type. (For allocations that hold a `T` in another form, like the
+    /// bytes of a `str`.)
+    pub fn allocated_as<T: ?Sized + 'static>(mut self) -> Self {
+        self.type_name = type_name::<T>();
+        self.type_id = Some(TypeId::of::<T>());
+        self
+    }
+
+    /// Mark this allocation as just holding a `Box<T>`, whose buffer has the layout `boxed`. Then `T`
+    /// is reported as the allocated type, and the buffer is counted in `size`.
+    pub fn holding_box<T: ?Sized + 'static>(mut self, boxed: Layout) -> Self {
+        self.boxed = Some(boxed);
+        self.allocated_as::<T>()
+    }
+
+    /// Move the data out, then free the allocation (and the `Box` it holds, if it holds one)
+    ///
+    /// Safety: `ptr` must point at the data in this allocation, which must be a `T`, and nothing else
+    /// can access the data or run its destructor
+    pub unsafe fn take<T>(self, ptr: *const T) -> T {
+        if self.boxed.is_some() {
+            // The allocation was made for a `Box<T>`, so it's aligned for one
+            #[allow(clippy::cast_ptr_alignment)]
+            let boxed = ptr::read(self.scan_ptr.cast::<Box<T>>());
+            self.free();
+            *boxed
+        } else {
+            let v = ptr::read(ptr);
+            self.free();
+            v
+        }
+    }
+
+    /// A pointer to the data, if this allocation holds a `T`
+    ///
+    /// Safety: The allocation must not have been freed yet
+    pub unsafe fn data_ptr<T: 'static>(&self) -> Option<*const T> {
+        if self.type_id != Some(TypeId::of::<T>()) {
+            return None;
+        }
+
+        if self.boxed.is_some() {
+            // The allocation was made for a `Box<T>`, so it's aligned for one
+            #[allow(clippy::cast_ptr_alignment)]
+            let boxed = self.scan_ptr.cast::<Box<T>>();
+            Some(ptr::addr_of!(**boxed))
+        } else {
+            Some(self.scan_ptr.cast::<T>())
+        }
     }
 
     pub fn scan<F: FnMut(InternalGcRef)>(&self, callback: F) {
@@ -140,6 +367,10 @@ impl GcAllocation {
     pub(crate) unsafe fn raw(v: *const dyn Scan) -> GcAllocation {
         GcAllocation {
             scan_ptr: v,
+            layout: Layout::for_value(&*v),
+            type_name: type_name::<dyn Scan>(),
+            type_id: None,
+            boxed: None,
             deallocation_action: DeallocationAction::DoNothing,
         }
     }
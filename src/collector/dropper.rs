@@ -1,57 +1,423 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::mem;
 use std::panic::catch_unwind;
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread::spawn;
+use std::thread::{self, ThreadId};
 
-use crossbeam::{SendError, Sender};
+use crossbeam::{Receiver, SendError, Sender};
+use crossbeam::{Select, TryRecvError};
+use parking_lot::{Condvar, Mutex};
 
 use crate::collector::GcData;
 
+/// What allocating threads should do when destructors fall behind
+///
+/// The "backlog" is the number of pieces of garbage that have been found by the collector, but
+/// haven't had their destructors run yet.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum DestructorBacklogPolicy {
+    /// Let the backlog grow without bound (this is the default)
+    #[default]
+    Unbounded,
+    /// Once the backlog exceeds `limit`, allocating threads run pending destructors themselves
+    Help {
+        /// The backlog size at which allocating threads start helping
+        limit: usize,
+    },
+    /// Once the backlog exceeds `limit`, allocating threads block until it shrinks below `limit`
+    Block {
+        /// The backlog size at which allocating threads start blocking
+        limit: usize,
+    },
+}
+
+/// Where the destructors for garbage are run
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum DestructorMode {
+    /// Destructors are run by a background thread (this is the default)
+    #[default]
+    Background,
+    /// Garbage is queued up until the application calls `run_pending_destructors`, so destructors
+    /// run on whatever thread the application chooses
+    CallerPumped,
+}
+
+thread_local! {
+    // Set while this thread is running destructors, so we never try to help (or block) re-entrantly
+    static RUNNING_DESTRUCTORS: Cell<bool> = const { Cell::new(false) };
+
+    // Present once this thread has allocated thread-affine data. Dropped when the thread exits
+    static THREAD_AFFINE_REGISTRATION: RefCell<Option<ThreadAffineRegistration>> = const { RefCell::new(None) };
+}
+
+/// Drains a thread's drop list when that thread exits
+struct ThreadAffineRegistration {
+    thread: ThreadId,
+    state: Arc<DropperState>,
+}
+
+impl Drop for ThreadAffineRegistration {
+    fn drop(&mut self) {
+        // After this, any more garbage owned by this thread can never have its destructor run
+        let remaining = self.state.thread_drop_lists.lock().remove(&self.thread);
+
+        if let Some(remaining) = remaining {
+            let _drop_guard = self.state.drop_lock.lock();
+            for data in remaining {
+                drop_data(&data, true);
+            }
+        }
+    }
+}
+
 pub(crate) struct BackgroundDropper {
     sender: Sender<DropMessage>,
+    state: Arc<DropperState>,
 }
 
 pub(crate) enum DropMessage {
-    DataToDrop(Arc<GcData>),
+    /// A batch of data, all of which is garbage and ready to be dropped
+    DataToDrop(Vec<Arc<GcData>>),
     SyncUp(Sender<()>),
 }
 
+struct DropperState {
+    receiver: Receiver<DropMessage>,
+    /// Whoever is running destructors (the background thread or a helper) must hold this lock
+    /// NOTE: All drops must happen linearly, otherwise there could be a race around the `deallocated` flag
+    drop_lock: Mutex<()>,
+    /// how many pieces of data have been sent to be dropped, but not dropped yet
+    backlog: AtomicUsize,
+    /// if true, garbage goes into `pumped_queue` instead of being sent to the drop thread
+    caller_pumped: AtomicBool,
+    /// garbage waiting for the application to call `run_pending_destructors`
+    pumped_queue: Mutex<VecDeque<Arc<GcData>>>,
+    /// thread-affine garbage, waiting for its owning thread to drop it
+    /// (a thread only has an entry here while it's alive)
+    thread_drop_lists: Mutex<HashMap<ThreadId, Vec<Arc<GcData>>>>,
+    /// the backlog size past which we apply backpressure (`usize::MAX` means unbounded)
+    backlog_limit: AtomicUsize,
+    /// if true we apply backpressure by blocking, otherwise we apply it by helping
+    block_on_backlog: AtomicBool,
+    /// blocked allocating threads wait on this condvar for the backlog to shrink
+    backlog_mutex: Mutex<()>,
+    backlog_condvar: Condvar,
+}
+
 impl BackgroundDropper {
     pub fn new() -> BackgroundDropper {
         let (sender, receiver) = crossbeam::unbounded();
 
+        let state = Arc::new(DropperState {
+            receiver,
+            drop_lock: Mutex::new(()),
+            backlog: AtomicUsize::new(0),
+            caller_pumped: AtomicBool::new(false),
+            pumped_queue: Mutex::new(VecDeque::new()),
+            thread_drop_lists: Mutex::new(HashMap::new()),
+            backlog_limit: AtomicUsize::new(usize::MAX),
+            block_on_backlog: AtomicBool::new(false),
+            backlog_mutex: Mutex::new(()),
+            backlog_condvar: Condvar::new(),
+        });
+
         // The drop thread deals with doing all the Drops this collector needs to do
+        let thread_state = state.clone();
         spawn(move || {
-            // An Err value means the stream will never recover
-            while let Ok(drop_msg) = receiver.recv() {
-                match drop_msg {
-                    DropMessage::DataToDrop(data) => {
-                        // Mark this data as in the process of being deallocated and unsafe to access
-                        // NOTE: All drops must be linearly in one thread, otherwise there could be a race around this flag being set
-                        data.deallocated.store(true, Ordering::SeqCst);
-
-                        // Deallocate / Run Drop
-                        let underlying_allocation = data.underlying_allocation;
-                        let res = catch_unwind(move || unsafe {
-                            underlying_allocation.deallocate();
-                        });
-                        if let Err(e) = res {
-                            eprintln!("Gc background drop failed: {:?}", e);
-                        }
-                    }
-                    DropMessage::SyncUp(responder) => {
-                        if let Err(e) = responder.send(()) {
-                            eprintln!("Gc background syncup failed: {:?}", e);
-                        }
-                    }
+            let receiver = &thread_state.receiver;
+            loop {
+                // Wait for a message, but don't take it until we hold the drop lock
+                // (otherwise a helping thread could handle a later message before we handle this one)
+                let mut select = Select::new();
+                select.recv(receiver);
+                select.ready();
+
+                let _drop_guard = thread_state.drop_lock.lock();
+                match receiver.try_recv() {
+                    Ok(drop_msg) => thread_state.handle_msg(drop_msg),
+                    // A helping thread must have beaten us to the message
+                    Err(TryRecvError::Empty) => {}
+                    // An Err value means the stream will never recover
+                    Err(TryRecvError::Disconnected) => break,
                 }
             }
         });
 
-        BackgroundDropper { sender }
+        BackgroundDropper { sender, state }
     }
 
     pub fn send_msg(&self, msg: DropMessage) -> Result<(), SendError<DropMessage>> {
+        if let DropMessage::DataToDrop(batch) = msg {
+            let batch = self.state.route_thread_affine(batch);
+            if batch.is_empty() {
+                return Ok(());
+            }
+
+            // We check the mode while holding the queue lock, so `set_mode` can't strand any data
+            let mut pumped_queue = self.state.pumped_queue.lock();
+            if self.state.caller_pumped.load(Ordering::SeqCst) {
+                pumped_queue.extend(batch);
+                return Ok(());
+            }
+
+            self.state.backlog.fetch_add(batch.len(), Ordering::SeqCst);
+            return self.sender.send(DropMessage::DataToDrop(batch));
+        }
+
         self.sender.send(msg)
     }
+
+    /// Drop data that's already been claimed (by setting its `deallocated` flag), on this thread
+    pub fn drop_claimed(&self, claimed: &[Arc<GcData>]) {
+        // If we're already running destructors, we already hold the drop lock
+        if RUNNING_DESTRUCTORS.with(Cell::get) {
+            for data in claimed {
+                run_destructor(data, true);
+            }
+            return;
+        }
+
+        // Even if `claimed` is empty, this waits out any drop that's already in progress
+        let _drop_guard = self.state.drop_lock.lock();
+        for data in claimed {
+            run_destructor(data, true);
+        }
+    }
+
+    pub fn backlog(&self) -> usize {
+        let thread_affine_backlog: usize = self
+            .state
+            .thread_drop_lists
+            .lock()
+            .values()
+            .map(Vec::len)
+            .sum();
+
+        self.state.backlog.load(Ordering::SeqCst)
+            + self.state.pumped_queue.lock().len()
+            + thread_affine_backlog
+    }
+
+    pub fn register_current_thread(&self) {
+        // If this thread is already exiting, we can't register (so its garbage will never be dropped)
+        let registration_res = THREAD_AFFINE_REGISTRATION.try_with(|registration| {
+            let mut registration = registration.borrow_mut();
+            if registration.is_none() {
+                let thread = thread::current().id();
+                self.state
+                    .thread_drop_lists
+                    .lock()
+                    .insert(thread, Vec::new());
+                *registration = Some(ThreadAffineRegistration {
+                    thread,
+                    state: self.state.clone(),
+                });
+            }
+        });
+
+        if registration_res.is_err() {
+            warn!(
+                "Allocated thread-affine data on an exiting thread. Its destructor will never run!"
+            );
+        }
+    }
+
+    /// Run the destructors for all garbage owned by this thread, returning how many ran
+    pub fn drain_current_thread(&self) -> usize {
+        // A destructor draining destructors would deadlock on the drop lock
+        if RUNNING_DESTRUCTORS.with(Cell::get) {
+            return 0;
+        }
+
+        let to_drop = match self
+            .state
+            .thread_drop_lists
+            .lock()
+            .get_mut(&thread::current().id())
+        {
+            Some(list) => mem::take(list),
+            None => return 0,
+        };
+
+        let _drop_guard = self.state.drop_lock.lock();
+        for data in &to_drop {
+            drop_data(data, true);
+        }
+        to_drop.len()
+    }
+
+    pub fn is_caller_pumped(&self) -> bool {
+        self.state.caller_pumped.load(Ordering::SeqCst)
+    }
+
+    pub fn set_mode(&self, mode: DestructorMode) -> Result<(), SendError<DropMessage>> {
+        let leftover_batch: Vec<_> = {
+            let mut pumped_queue = self.state.pumped_queue.lock();
+            self.state
+                .caller_pumped
+                .store(mode == DestructorMode::CallerPumped, Ordering::SeqCst);
+            pumped_queue.drain(..).collect()
+        };
+
+        if leftover_batch.is_empty() {
+            return Ok(());
+        }
+
+        // Whatever was waiting to be pumped is handed off in the new mode
+        self.send_msg(DropMessage::DataToDrop(leftover_batch))
+    }
+
+    /// Run up to `max` destructors from the pumped queue on this thread, returning how many ran
+    pub fn run_pending(&self, max: usize) -> usize {
+        // A destructor pumping destructors would deadlock on the drop lock
+        if RUNNING_DESTRUCTORS.with(Cell::get) {
+            return 0;
+        }
+
+        let _drop_guard = self.state.drop_lock.lock();
+        let mut ran = 0;
+        while ran < max {
+            let next = self.state.pumped_queue.lock().pop_front();
+            match next {
+                Some(data) => {
+                    drop_data(&data, false);
+                    ran += 1;
+                }
+                None => break,
+            }
+        }
+        ran
+    }
+
+    pub fn set_backlog_policy(&self, policy: DestructorBacklogPolicy) {
+        let (limit, block) = match policy {
+            DestructorBacklogPolicy::Unbounded => (usize::MAX, false),
+            DestructorBacklogPolicy::Help { limit } => (limit, false),
+            DestructorBacklogPolicy::Block { limit } => (limit, true),
+        };
+
+        {
+            // Take the lock so blocked threads see the new policy when they wake up
+            let _guard = self.state.backlog_mutex.lock();
+            self.state.block_on_backlog.store(block, Ordering::SeqCst);
+            self.state.backlog_limit.store(limit, Ordering::SeqCst);
+        }
+        self.state.backlog_condvar.notify_all();
+    }
+
+    /// Called by allocating threads. If the backlog is too large, help out or block
+    pub fn apply_backpressure(&self) {
+        let state = &*self.state;
+
+        if state.backlog.load(Ordering::SeqCst) <= state.backlog_limit.load(Ordering::SeqCst) {
+            return;
+        }
+
+        // If we're already running destructors, then helping/blocking would deadlock
+        if RUNNING_DESTRUCTORS.with(Cell::get) {
+            return;
+        }
+
+        if state.block_on_backlog.load(Ordering::SeqCst) {
+            let mut guard = state.backlog_mutex.lock();
+            while state.backlog.load(Ordering::SeqCst) > state.backlog_limit.load(Ordering::SeqCst)
+                && state.block_on_backlog.load(Ordering::SeqCst)
+            {
+                state.backlog_condvar.wait(&mut guard);
+            }
+        } else {
+            while state.backlog.load(Ordering::SeqCst) > state.backlog_limit.load(Ordering::SeqCst)
+            {
+                let _drop_guard = state.drop_lock.lock();
+                match state.receiver.try_recv() {
+                    Ok(drop_msg) => state.handle_msg(drop_msg),
+                    Err(_) => return,
+                }
+            }
+        }
+    }
+}
+
+impl DropperState {
+    // Moves thread-affine data onto the drop list of its thread, returning everything else
+    fn route_thread_affine(&self, batch: Vec<Arc<GcData>>) -> Vec<Arc<GcData>> {
+        if batch.iter().all(|data| data.owning_thread.is_none()) {
+            return batch;
+        }
+
+        let mut thread_drop_lists = self.thread_drop_lists.lock();
+        let mut remaining = Vec::with_capacity(batch.len());
+        for data in batch {
+            if let Some(list) = data
+                .owning_thread
+                .and_then(|owner| thread_drop_lists.get_mut(&owner))
+            {
+                list.push(data);
+            } else {
+                // If the owning thread has exited this data stays in the batch (and gets leaked)
+                remaining.push(data);
+            }
+        }
+        remaining
+    }
+
+    // Must be called with the `drop_lock` held
+    fn handle_msg(&self, drop_msg: DropMessage) {
+        match drop_msg {
+            DropMessage::DataToDrop(batch) => {
+                let batch_size = batch.len();
+                for data in batch {
+                    drop_data(&data, false);
+                }
+
+                self.backlog.fetch_sub(batch_size, Ordering::SeqCst);
+                {
+                    // Take the lock so a blocked thread can't miss this notification
+                    let _guard = self.backlog_mutex.lock();
+                }
+                self.backlog_condvar.notify_all();
+            }
+            DropMessage::SyncUp(responder) => {
+                if let Err(e) = responder.send(()) {
+                    error!("Gc background syncup failed: {:?}", e);
+                }
+            }
+        }
+    }
+}
+
+// Must be called with the `drop_lock` held
+fn drop_data(data: &GcData, on_owning_thread: bool) {
+    // Mark this data as in the process of being deallocated and unsafe to access
+    // (If it's already marked, `destroy_now` has claimed it, and will drop it itself)
+    if data.deallocated.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    run_destructor(data, on_owning_thread);
+}
+
+// Must be called with the `drop_lock` held, after setting the `deallocated` flag
+fn run_destructor(data: &GcData, on_owning_thread: bool) {
+    let was_running_destructors = RUNNING_DESTRUCTORS.with(|r| r.replace(true));
+
+    // Deallocate / Run Drop
+    // (Unless this is thread-affine data and we're not on its thread. Then it gets leaked.)
+    let run_destructor = on_owning_thread || data.owning_thread.is_none();
+    let underlying_allocation = data.underlying_allocation;
+    let res = catch_unwind(move || unsafe {
+        if run_destructor {
+            underlying_allocation.deallocate();
+        } else {
+            underlying_allocation.deallocate_without_drop();
+        }
+    });
+    if let Err(e) = res {
+        error!("Gc drop failed: {:?}", e);
+    }
+
+    RUNNING_DESTRUCTORS.with(|r| r.set(was_running_destructors));
 }
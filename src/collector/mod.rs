@@ -2,11 +2,18 @@ mod alloc;
 mod dropper;
 mod trigger;
 
+use std::alloc::Layout;
+use std::any::TypeId;
 use std::cmp;
 use std::hash::{Hash, Hasher};
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::task::{Context, Poll};
 use std::thread::spawn;
+use std::thread::{self, ThreadId};
+use std::time::Instant;
 
 use crossbeam::queue::SegQueue;
 use crossbeam::Sender;
@@ -18,9 +25,31 @@ use rayon::iter::{IntoParallelIterator, ParallelBridge, ParallelIterator};
 
 use crate::collector::alloc::GcAllocation;
 use crate::collector::dropper::{BackgroundDropper, DropMessage};
+pub use crate::collector::dropper::{DestructorBacklogPolicy, DestructorMode};
 use crate::collector::trigger::GcTrigger;
+use crate::context::{
+    current_context, AllocationContext, ContextAccounting, ContextStats, Reservation,
+};
 use crate::lockout::{ExclusiveWarrant, Lockout, LockoutProvider, Warrant};
-use crate::{Finalize, Scan};
+use crate::{Finalize, GcError, Scan};
+
+/// How long `destroy_now` waits for guards on the data to be released
+// The infallible ways of accessing data panic on the errors `check_data_warrant` finds
+fn expect_data_warrant<W>(res: Result<W, GcError>) -> W {
+    match res {
+        Ok(warrant) => warrant,
+        Err(GcError::Uninitialized) => {
+            panic!("Tried to access a Gc before `Gc::new_cyclic` finished constructing its data")
+        }
+        Err(GcError::QuotaExceeded) => unreachable!("accessing data never checks a quota"),
+        Err(GcError::Deallocated | GcError::Timeout) => panic!("Tried to access into a Gc, but the internal state was corrupted (perhaps you're manipulating Gc<?> in a destructor, or after its scope ended?)"),
+    }
+}
+
+// An estimate of the memory a `DashMap` has reserved (each bucket also has a control byte)
+fn map_bytes<K: Eq + Hash, V>(map: &DashMap<K, V>) -> usize {
+    map.capacity() * (mem::size_of::<(K, V)>() + 1)
+}
 
 /// Intermediate struct. `Gc<T>` holds a `InternalGcRef`, which references a `GcHandle`
 /// There should be one `GcHandle` per `Gc<T>`
@@ -37,6 +66,31 @@ impl InternalGcRef {
     pub(crate) fn invalidate(&self) {
         COLLECTOR.drop_handle(self);
     }
+
+    pub(crate) fn data(&self) -> Arc<GcData> {
+        self.handle_ref.underlying_data.clone()
+    }
+
+    /// The unique id of the data this handle points at
+    pub(crate) fn data_id(&self) -> u64 {
+        self.handle_ref.underlying_data.unique_id
+    }
+
+    /// The type the data this handle points at was allocated as, if it's known
+    pub(crate) fn type_id(&self) -> Option<TypeId> {
+        self.handle_ref
+            .underlying_data
+            .underlying_allocation
+            .type_id()
+    }
+
+    /// The name of the type the data this handle points at was allocated as
+    pub(crate) fn type_name(&self) -> &'static str {
+        self.handle_ref
+            .underlying_data
+            .underlying_allocation
+            .type_name()
+    }
 }
 
 /// We don't want to expose what specific warrant provider we're using
@@ -47,6 +101,12 @@ pub struct GcGuardWarrant {
 }
 type GcExclusiveWarrant = ExclusiveWarrant<Arc<GcData>>;
 
+/// Like `GcGuardWarrant`, but nothing else can access the data while it's held
+pub struct GcGuardExclusiveWarrant {
+    /// stores the internal warrant. only the drop being run is relevant
+    _warrant: GcExclusiveWarrant,
+}
+
 pub struct Collector {
     /// just a monotonic counter. used to assign unique ids
     monotonic_counter: AtomicU64,
@@ -62,6 +122,40 @@ pub struct Collector {
     async_gc_notifier: Sender<()>,
     /// all the data we are managing plus metadata about what `Gc<T>`s exist
     tracked_data: TrackedData,
+    /// set by `PoisonPolicy::Ignore`
+    ignore_poison: AtomicBool,
+    /// per-`AllocationContext` totals of the tracked data
+    contexts: ContextAccounting,
+}
+
+/// What locking a poisoned `Gc<Mutex<T>>` or `Gc<RwLock<T>>` does
+///
+/// Either way, the collector always scans through poisoned locks. So any `Gc`s inside poisoned
+/// data stay alive for as long as the lock itself is reachable.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum PoisonPolicy {
+    /// Locking returns a `GcPoisonError`, like `std` does (this is the default)
+    #[default]
+    Report,
+    /// Locking succeeds as if the lock wasn't poisoned
+    Ignore,
+}
+
+/// How much heap memory the collector is using, as reported by `heap_bytes`
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct HeapBytes {
+    /// the bytes taken up by the data in each `Gc`
+    pub payload: usize,
+    /// an estimate of the bytes the collector uses to keep track of that data, and of each `Gc`
+    pub metadata: usize,
+}
+
+impl HeapBytes {
+    /// The total bytes in use, `payload + metadata`
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.payload + self.metadata
+    }
 }
 
 /// Stores metadata about each piece of tracked data, plus metadata about each handle
@@ -74,6 +168,8 @@ struct TrackedData {
     data: DashMap<Arc<GcData>, ()>,
     /// a set storing metadata on each live handle (`Gc<T>`) the collector is managing
     handles: DashMap<Arc<GcHandle>, ()>,
+    /// the addresses of the boxed handles given away by `Gc::into_raw`, and the size of each box
+    raw_handles: DashMap<usize, usize>,
 }
 
 /// Represents a piece of data tracked by the collector
@@ -86,9 +182,17 @@ pub(crate) struct GcData {
     lockout: Lockout,
     /// have we started deallocating this piece of data yet?
     deallocated: AtomicBool,
+    /// has the data been written yet? (only false while `Gc::new_cyclic` is constructing it)
+    initialized: AtomicBool,
     // During what collection was this last marked?
     //     0 if this is a new piece of data
     last_marked: AtomicU64,
+    /// if set, this data must be dropped on the given thread
+    owning_thread: Option<ThreadId>,
+    /// the context this data was allocated in, if any
+    context: Option<AllocationContext>,
+    /// how many `GcHandle`s (and thus `Gc<T>`s) point at this data
+    handle_count: AtomicUsize,
 }
 
 impl LockoutProvider for Arc<GcData> {
@@ -182,19 +286,24 @@ impl Collector {
                 current_collection_number: AtomicU64::new(2),
                 data: DashMap::new(),
                 handles: DashMap::new(),
+                raw_handles: DashMap::new(),
             },
+            ignore_poison: AtomicBool::new(false),
+            contexts: ContextAccounting::default(),
         });
 
         // The async Gc thread deals with background Gc'ing
-        let async_collector_ref = Arc::downgrade(&res);
-        spawn(move || {
-            // An Err value means the stream will never recover
-            while async_gc_receiver.recv().is_ok() {
-                if let Some(collector) = async_collector_ref.upgrade() {
-                    collector.check_then_collect();
+        {
+            let async_collector_ref = Arc::downgrade(&res);
+            spawn(move || {
+                // An Err value means the stream will never recover
+                while async_gc_receiver.recv().is_ok() {
+                    if let Some(collector) = async_collector_ref.upgrade() {
+                        collector.check_then_collect();
+                    }
                 }
-            }
-        });
+            });
+        }
 
         res
     }
@@ -219,12 +328,89 @@ impl Collector {
 
     pub fn track_with_drop<T: Scan + 'static>(&self, data: T) -> (InternalGcRef, *const T) {
         let (gc_data_ptr, heap_ptr) = GcAllocation::allocate_with_drop(data);
-        self.track(gc_data_ptr, heap_ptr)
+        self.track(gc_data_ptr, heap_ptr, None)
+    }
+
+    /// Like `track_with_drop`, but fails instead of taking the current context past its quota
+    pub fn try_track_with_drop<T: Scan + 'static>(
+        &self,
+        data: T,
+    ) -> Result<(InternalGcRef, *const T), GcError> {
+        let (gc_data_ptr, heap_ptr) = GcAllocation::allocate_with_drop(data);
+
+        // Reserve the space before tracking, so allocations racing for the quota can't overshoot.
+        // (This charges the same bytes `start_tracking` would, so stats and quotas agree.)
+        let reservation = match current_context() {
+            Some(context) => match self.contexts.try_reserve(context, gc_data_ptr.size()) {
+                Ok(reservation) => Some(reservation),
+                Err(e) => {
+                    // Safety: Nothing else has seen this allocation
+                    unsafe { gc_data_ptr.deallocate() };
+                    return Err(e);
+                }
+            },
+            None => None,
+        };
+
+        let (handle, new_data) = self.create_data_and_handle(gc_data_ptr, None, true);
+        match reservation {
+            Some(reservation) => self.start_tracking_reserved(new_data, reservation),
+            None => self.start_tracking(new_data),
+        }
+        self.after_allocation();
+
+        Ok((handle, heap_ptr))
+    }
+
+    /// Like `track_with_drop`, but the allocation reports itself as a `T` rather than a `Box<T>`
+    /// (and its size includes what the `Box` points to)
+    pub fn track_box_with_drop<T: Scan + ?Sized + 'static>(
+        &self,
+        data: Box<T>,
+    ) -> (InternalGcRef, *const Box<T>) {
+        let boxed = Layout::for_value(&*data);
+        let (gc_data_ptr, heap_ptr) = GcAllocation::allocate_with_drop(data);
+        self.track(gc_data_ptr.holding_box::<T>(boxed), heap_ptr, None)
+    }
+
+    pub fn track_thread_affine<T: Scan + 'static>(&self, data: T) -> (InternalGcRef, *const T) {
+        // Make sure this thread's drop list is drained when the thread exits
+        self.dropper.register_current_thread();
+
+        let (gc_data_ptr, heap_ptr) = GcAllocation::allocate_with_drop(data);
+        self.track(gc_data_ptr, heap_ptr, Some(thread::current().id()))
+    }
+
+    /// The data is thread-affine: it may not be `Send`, so if it's collected early its destructor
+    /// still runs on this thread
+    ///
+    /// Safety: `destroy_now` must be called on this data (on this thread) before `T`'s lifetime ends
+    pub unsafe fn track_with_unchecked_drop<T: Scan>(&self, data: T) -> (InternalGcRef, *const T) {
+        self.dropper.register_current_thread();
+
+        let (gc_data_ptr, heap_ptr) = GcAllocation::allocate_with_unchecked_drop(data);
+        self.track(gc_data_ptr, heap_ptr, Some(thread::current().id()))
+    }
+
+    pub fn track_slice_with_drop<T: Scan + 'static>(
+        &self,
+        data: Vec<T>,
+    ) -> (InternalGcRef, *const [T]) {
+        let (gc_data_ptr, heap_ptr) = GcAllocation::allocate_slice_with_drop(data);
+        self.track(gc_data_ptr, heap_ptr, None)
+    }
+
+    /// Like `track_slice_with_drop`, but the allocation reports itself as a `str`
+    pub fn track_str_with_drop(&self, data: String) -> (InternalGcRef, *const str) {
+        let (gc_data_ptr, heap_ptr) = GcAllocation::allocate_slice_with_drop(data.into_bytes());
+        let (handle, ptr) = self.track(gc_data_ptr.allocated_as::<str>(), heap_ptr, None);
+        // Safety: These bytes came from a `String`, so they're valid UTF-8
+        (handle, ptr as *const str)
     }
 
     pub fn track_with_no_drop<T: Scan>(&self, data: T) -> (InternalGcRef, *const T) {
         let (gc_data_ptr, heap_ptr) = GcAllocation::allocate_no_drop(data);
-        self.track(gc_data_ptr, heap_ptr)
+        self.track(gc_data_ptr, heap_ptr, None)
     }
 
     pub fn track_with_finalization<T: Finalize + Scan>(
@@ -232,20 +418,108 @@ impl Collector {
         data: T,
     ) -> (InternalGcRef, *const T) {
         let (gc_data_ptr, heap_ptr) = GcAllocation::allocate_with_finalization(data);
-        self.track(gc_data_ptr, heap_ptr)
+        self.track(gc_data_ptr, heap_ptr, None)
     }
 
-    fn track<T: Scan>(
+    /// Allocate space for a `T` and create a handle to it, without writing any data there yet
+    ///
+    /// The data isn't tracked until `initialize` is called, so the collector won't scan it. (Though
+    /// handles to it can be created and dropped as normal.)
+    pub fn track_uninit_with_drop<T: Scan + 'static>(&self) -> (InternalGcRef, *const T) {
+        let (gc_data_ptr, heap_ptr) = GcAllocation::allocate_uninit_with_drop();
+        let (handle, _) = self.create_data_and_handle(gc_data_ptr, None, false);
+        (handle, heap_ptr)
+    }
+
+    /// Write the data for an allocation from `track_uninit_with_drop`, and start tracking it
+    ///
+    /// Safety: `handle` and `ptr` must have come from the same `track_uninit_with_drop` call, and
+    /// this (or `abandon_uninit`) must only be called once
+    pub unsafe fn initialize<T: Scan>(&self, handle: &InternalGcRef, ptr: *const T, v: T) {
+        ptr::write(ptr.cast_mut(), v);
+
+        let data = handle.data();
+        data.initialized.store(true, Ordering::SeqCst);
+        self.start_tracking(data);
+
+        self.after_allocation();
+    }
+
+    /// Free an allocation from `track_uninit_with_drop` that will never be initialized
+    ///
+    /// Any remaining handles to it will panic on access.
+    ///
+    /// Safety: Same as `initialize`
+    #[allow(clippy::unused_self)]
+    pub unsafe fn abandon_uninit(&self, handle: &InternalGcRef) {
+        let data = &handle.handle_ref.underlying_data;
+        // This data was never tracked, so we just need to stop anyone else claiming it
+        data.deallocated.store(true, Ordering::SeqCst);
+        data.underlying_allocation.free();
+    }
+
+    fn track<T: Scan + ?Sized>(
         &self,
         gc_data_ptr: GcAllocation,
         heap_ptr: *const T,
+        owning_thread: Option<ThreadId>,
     ) -> (InternalGcRef, *const T) {
+        let (handle, new_data) = self.create_data_and_handle(gc_data_ptr, owning_thread, true);
+        // The handle was inserted first -- we don't want the data to be observable before there is a relevant handle
+        // TODO: Ensure our map really promises these will appear in order
+        self.start_tracking(new_data);
+
+        self.after_allocation();
+
+        (handle, heap_ptr)
+    }
+
+    fn start_tracking(&self, data: Arc<GcData>) {
+        if let Some(context) = data.context {
+            self.contexts
+                .record_allocation(context, data.underlying_allocation.size());
+        }
+        self.tracked_data.data.insert(data, ());
+    }
+
+    // Like `start_tracking`, for data whose bytes were reserved in its context ahead of time
+    fn start_tracking_reserved(&self, data: Arc<GcData>, reservation: Reservation<'_>) {
+        debug_assert_eq!(data.context, Some(reservation.context()));
+        reservation.commit();
+        self.tracked_data.data.insert(data, ());
+    }
+
+    // Every path that stops tracking data goes through here, so each context's totals stay in sync
+    fn stop_tracking(&self, data: &Arc<GcData>) {
+        if self.tracked_data.data.remove(data).is_some() {
+            self.stopped_tracking(data);
+        }
+    }
+
+    // (For data that has already been removed from `tracked_data`)
+    fn stopped_tracking(&self, data: &GcData) {
+        if let Some(context) = data.context {
+            self.contexts
+                .record_deallocation(context, data.underlying_allocation.size());
+        }
+    }
+
+    fn create_data_and_handle(
+        &self,
+        gc_data_ptr: GcAllocation,
+        owning_thread: Option<ThreadId>,
+        initialized: bool,
+    ) -> (InternalGcRef, Arc<GcData>) {
         let new_data = Arc::new(GcData {
             unique_id: self.get_unique_id(),
             underlying_allocation: gc_data_ptr,
             lockout: Lockout::new(),
             deallocated: AtomicBool::new(false),
+            initialized: AtomicBool::new(initialized),
             last_marked: AtomicU64::new(0),
+            owning_thread,
+            context: current_context(),
+            handle_count: AtomicUsize::new(1),
         });
 
         let new_handle = Arc::new(GcHandle {
@@ -254,33 +528,47 @@ impl Collector {
             last_non_rooted: AtomicU64::new(0),
         });
 
-        {
-            // Insert handle before data -- don't want the data to be observable before there is a relevant handle
-            // TODO: Ensure our map really promises these will appear in order
-            self.tracked_data.handles.insert(new_handle.clone(), ());
-
-            self.tracked_data.data.insert(new_data, ());
-        }
+        self.tracked_data.handles.insert(new_handle.clone(), ());
 
-        let res = (InternalGcRef::new(new_handle), heap_ptr);
+        (InternalGcRef::new(new_handle), new_data)
+    }
 
+    fn after_allocation(&self) {
         // When we allocate, the heuristic for whether we need to GC might change
         self.notify_async_gc_thread();
 
-        res
+        // If destructors are falling behind, this thread may need to help out (or wait)
+        self.dropper.apply_backpressure();
     }
 
     pub fn drop_handle(&self, handle: &InternalGcRef) {
-        self.tracked_data.handles.remove(&handle.handle_ref);
+        if self
+            .tracked_data
+            .handles
+            .remove(&handle.handle_ref)
+            .is_some()
+        {
+            handle
+                .handle_ref
+                .underlying_data
+                .handle_count
+                .fetch_sub(1, Ordering::SeqCst);
+        }
 
         // NOTE: This is worth experimenting with
         // self.notify_async_gc_thread();
     }
 
     pub fn clone_handle(&self, handle: &InternalGcRef) -> InternalGcRef {
+        self.new_handle_for(&handle.handle_ref.underlying_data)
+    }
+
+    fn new_handle_for(&self, data: &Arc<GcData>) -> InternalGcRef {
+        data.handle_count.fetch_add(1, Ordering::SeqCst);
+
         let new_handle = Arc::new(GcHandle {
             unique_id: self.get_unique_id(),
-            underlying_data: handle.handle_ref.underlying_data.clone(),
+            underlying_data: data.clone(),
             last_non_rooted: AtomicU64::new(0),
         });
 
@@ -291,28 +579,229 @@ impl Collector {
         }
     }
 
-    #[allow(clippy::unused_self)]
     pub fn get_data_warrant(&self, handle: &InternalGcRef) -> GcGuardWarrant {
-        // This check is only necessary in the destructors
-        // The destructor thread will always set the `deallocated` flag before deallocating data
+        expect_data_warrant(self.checked_get_data_warrant(handle))
+    }
+
+    /// Like `get_data_warrant`, but returns an error instead of panicking if the data is gone
+    pub fn checked_get_data_warrant(
+        &self,
+        handle: &InternalGcRef,
+    ) -> Result<GcGuardWarrant, GcError> {
+        let warrant = Lockout::get_warrant(handle.handle_ref.underlying_data.clone());
+        self.check_data_warrant(handle, warrant)
+    }
+
+    /// Like `checked_get_data_warrant`, but gives up at `deadline`
+    pub fn get_data_warrant_until(
+        &self,
+        handle: &InternalGcRef,
+        deadline: Instant,
+    ) -> Result<GcGuardWarrant, GcError> {
+        let warrant =
+            Lockout::get_warrant_until(handle.handle_ref.underlying_data.clone(), deadline)
+                .ok_or(GcError::Timeout)?;
+        self.check_data_warrant(handle, warrant)
+    }
+
+    /// Like `get_data_warrant`, but registers a waker instead of blocking
+    pub fn poll_data_warrant(
+        &self,
+        handle: &InternalGcRef,
+        cx: &mut Context<'_>,
+    ) -> Poll<GcGuardWarrant> {
+        Lockout::poll_warrant(&handle.handle_ref.underlying_data, cx)
+            .map(|warrant| expect_data_warrant(self.check_data_warrant(handle, warrant)))
+    }
+
+    /// Does `handle` point at data that can be accessed? (This is false before `Gc::new_cyclic`
+    /// finishes, and after the data is deallocated)
+    #[allow(clippy::unused_self)]
+    pub fn is_live(&self, handle: &InternalGcRef) -> bool {
+        let data = &handle.handle_ref.underlying_data;
+        data.initialized.load(Ordering::SeqCst) && !data.deallocated.load(Ordering::SeqCst)
+    }
+
+    fn check_data_warrant(
+        &self,
+        handle: &InternalGcRef,
+        warrant: Warrant<Arc<GcData>>,
+    ) -> Result<GcGuardWarrant, GcError> {
+        self.check_data(handle)?;
+        Ok(GcGuardWarrant { _warrant: warrant })
+    }
+
+    // Checks that `handle`'s data can be accessed, once we hold a warrant for it
+    #[allow(clippy::unused_self)]
+    fn check_data(&self, handle: &InternalGcRef) -> Result<(), GcError> {
+        // Until `Gc::new_cyclic` finishes, there's nothing here to access
+        let data_initialized = handle
+            .handle_ref
+            .underlying_data
+            .initialized
+            .load(Ordering::SeqCst);
+        if !data_initialized {
+            return Err(GcError::Uninitialized);
+        }
+
+        // This check is only necessary in the destructors, or after a scope has ended
+        // The `deallocated` flag is always set before deallocating data. (And we check it after
+        // taking the warrant, since `destroy_now` sets it while holding an exclusive warrant.)
         let data_deallocated = handle
             .handle_ref
             .underlying_data
             .deallocated
             .load(Ordering::SeqCst);
         if data_deallocated {
-            panic!("Tried to access into a Gc, but the internal state was corrupted (perhaps you're manipulating Gc<?> in a destructor?)");
+            return Err(GcError::Deallocated);
         }
 
-        GcGuardWarrant {
-            _warrant: Lockout::get_warrant(handle.handle_ref.underlying_data.clone()),
+        Ok(())
+    }
+
+    /// If `handle` is the only handle pointing at its data, get an exclusive warrant for the data.
+    /// (This blocks while anything else is accessing the data, like the collector.)
+    pub fn get_unique_data_warrant(
+        &self,
+        handle: &InternalGcRef,
+    ) -> Option<GcGuardExclusiveWarrant> {
+        if !self.is_unique(handle) {
+            return None;
         }
+
+        let warrant = Lockout::wait_for_exclusive_warrant(&handle.handle_ref.underlying_data, None)
+            .expect("waiting without a deadline always gets a warrant");
+        // `live_objects` may have made a new handle before we got the warrant (but it can't now)
+        if !self.is_unique(handle) {
+            return None;
+        }
+
+        expect_data_warrant(self.check_data(handle));
+        Some(GcGuardExclusiveWarrant { _warrant: warrant })
+    }
+
+    /// Is `handle` the only handle pointing at its data?
+    /// (Note that handles inside other data are counted too)
+    #[allow(clippy::unused_self)]
+    pub fn is_unique(&self, handle: &InternalGcRef) -> bool {
+        handle
+            .handle_ref
+            .underlying_data
+            .handle_count
+            .load(Ordering::SeqCst)
+            == 1
+    }
+
+    /// If `handle` is the only handle to its data, stop tracking the data and move it out
+    ///
+    /// Safety: `ptr` must be the pointer to the data, returned when `handle`'s data was tracked
+    pub unsafe fn try_take<T: Scan>(&self, handle: &InternalGcRef, ptr: *const T) -> Option<T> {
+        // The caller owns `handle`, so if it's unique nobody else can create a new one
+        if !self.is_unique(handle) {
+            return None;
+        }
+
+        let data = &handle.handle_ref.underlying_data;
+
+        // Wait for the collector to finish scanning this data (if it is)
+        let warrant = Lockout::wait_for_exclusive_warrant(data, None)
+            .expect("waiting without a deadline always gets a warrant");
+        // `live_objects` may have made a new handle before we got the warrant (but it can't now)
+        if !self.is_unique(handle) {
+            return None;
+        }
+
+        // This claims the data, like the dropper would. (Unless a scope has already destroyed it)
+        if data.deallocated.swap(true, Ordering::SeqCst) {
+            return None;
+        }
+        self.stop_tracking(data);
+        drop(warrant);
+
+        // Now nothing else can access the data, so we can move it out and free the allocation
+        Some(data.underlying_allocation.take(ptr))
+    }
+
+    /// Create a handle to every live allocation that holds exactly a `T`
+    ///
+    /// Data someone has exclusive access to (through `get_unique_data_warrant` or `try_take`) is
+    /// skipped, since a new handle would break the uniqueness they rely on.
+    pub fn live_objects<T: 'static>(&self) -> Vec<(InternalGcRef, *const T)> {
+        // Hold off collection, so nothing we find is on its way to the dropper
+        let _gc_guard = self.gc_lock.lock();
+
+        self.tracked_data
+            .data
+            .iter()
+            .filter_map(|entry| {
+                let data = entry.key();
+                if data.underlying_allocation.type_id() != Some(TypeId::of::<T>()) {
+                    return None;
+                }
+
+                // Holding a warrant while we make the handle keeps exclusive access out
+                let _warrant = Lockout::try_get_warrant(data.clone())?;
+                if data.deallocated.load(Ordering::SeqCst) {
+                    return None;
+                }
+                // Safety: The data can't be freed while we hold a warrant and it isn't deallocated
+                let ptr = unsafe { data.underlying_allocation.data_ptr::<T>()? };
+                Some((self.new_handle_for(data), ptr))
+            })
+            .collect()
     }
 
     pub fn tracked_data_count(&self) -> usize {
         self.tracked_data.data.len()
     }
 
+    /// Remember the boxed handle at `addr` (taking up `size` bytes), given away by `Gc::into_raw`
+    pub fn stash_raw_handle(&self, addr: usize, size: usize) {
+        self.tracked_data.raw_handles.insert(addr, size);
+    }
+
+    /// Forget a boxed handle remembered by `stash_raw_handle`, returning `false` if it wasn't there
+    pub fn reclaim_raw_handle(&self, addr: usize) -> bool {
+        self.tracked_data.raw_handles.remove(&addr).is_some()
+    }
+
+    pub fn heap_bytes(&self) -> HeapBytes {
+        // An `Arc`'s allocation has a strong and a weak count before the value
+        const ARC_COUNTS: usize = 2 * mem::size_of::<usize>();
+
+        let mut bytes = HeapBytes::default();
+        for entry in &self.tracked_data.data {
+            let data = entry.key();
+            bytes.payload += data.underlying_allocation.size();
+            // (The `Lockout` lives inside `GcData`, but may own a buffer of wakers)
+            bytes.metadata += ARC_COUNTS + mem::size_of::<GcData>() + data.lockout.heap_bytes();
+        }
+
+        bytes.metadata +=
+            self.tracked_data.handles.len() * (ARC_COUNTS + mem::size_of::<GcHandle>());
+        for entry in &self.tracked_data.raw_handles {
+            bytes.metadata += entry.value();
+        }
+
+        bytes.metadata += map_bytes(&self.tracked_data.data)
+            + map_bytes(&self.tracked_data.handles)
+            + map_bytes(&self.tracked_data.raw_handles);
+
+        bytes
+    }
+
+    pub fn set_context_quota(&self, context: AllocationContext, quota: Option<usize>) {
+        self.contexts.set_quota(context, quota);
+    }
+
+    pub fn context_stats(&self, context: AllocationContext) -> ContextStats {
+        self.contexts.stats(context)
+    }
+
+    pub fn all_context_stats(&self) -> Vec<(AllocationContext, ContextStats)> {
+        self.contexts.all_stats()
+    }
+
     pub fn handle_count(&self) -> usize {
         self.tracked_data.handles.len()
     }
@@ -321,7 +810,79 @@ impl Collector {
         self.trigger.set_trigger_percent(new_trigger_percent);
     }
 
+    pub fn set_destructor_backlog_policy(&self, policy: DestructorBacklogPolicy) {
+        self.dropper.set_backlog_policy(policy);
+    }
+
+    pub fn set_poison_policy(&self, policy: PoisonPolicy) {
+        self.ignore_poison
+            .store(policy == PoisonPolicy::Ignore, Ordering::SeqCst);
+    }
+
+    pub fn poison_policy(&self) -> PoisonPolicy {
+        if self.ignore_poison.load(Ordering::SeqCst) {
+            PoisonPolicy::Ignore
+        } else {
+            PoisonPolicy::Report
+        }
+    }
+
+    pub fn pending_destructor_count(&self) -> usize {
+        self.dropper.backlog()
+    }
+
+    pub fn set_destructor_mode(&self, mode: DestructorMode) {
+        if let Err(e) = self.dropper.set_mode(mode) {
+            error!("Error sending to drop thread {e}");
+        }
+    }
+
+    pub fn run_pending_destructors(&self, max: usize) -> usize {
+        self.dropper.run_pending(max)
+    }
+
+    pub fn drain_thread_local_drops(&self) -> usize {
+        self.dropper.drain_current_thread()
+    }
+
     pub fn synchronize_destructors(&self) {
+        self.synchronize_drop_thread();
+
+        // In caller-pumped mode, the caller is the one that runs destructors
+        if self.dropper.is_caller_pumped() {
+            self.run_pending_destructors(usize::MAX);
+        }
+    }
+
+    /// Destroy the given data right now, even if it is still reachable
+    ///
+    /// Blocks until nothing is accessing the data. Afterwards, any `Gc`s still pointing at it will
+    /// panic on access.
+    pub fn destroy_now(&self, to_destroy: &[Arc<GcData>]) {
+        // We don't take the gc lock here, since a destructor may be running this method (and a
+        // collection could be waiting on that destructor). Instead we rely on the collector only
+        // scanning data it holds an exclusive warrant for, and checking `deallocated` first.
+        let mut claimed = Vec::with_capacity(to_destroy.len());
+        for data in to_destroy {
+            // Wait for any guards on this data to be released
+            let _warrant = Lockout::wait_for_exclusive_warrant(data, None);
+
+            // If this data was already claimed by the dropper, it's the dropper's job to drop it
+            if data.deallocated.swap(true, Ordering::SeqCst) {
+                continue;
+            }
+
+            claimed.push(data.clone());
+        }
+
+        for data in &claimed {
+            self.stop_tracking(data);
+        }
+
+        self.dropper.drop_claimed(&claimed);
+    }
+
+    fn synchronize_drop_thread(&self) {
         // We send a channel to the drop thread and wait for it to respond
         // This has the effect of synchronizing this thread with the drop thread
 
@@ -338,12 +899,7 @@ impl Collector {
     pub fn check_then_collect(&self) -> bool {
         let gc_guard = self.gc_lock.lock();
 
-        let current_data_count = self.tracked_data.data.len();
-        let current_handle_count = self.tracked_data.handles.len();
-        if self
-            .trigger
-            .should_collect(current_data_count, current_handle_count)
-        {
+        if self.should_collect() {
             self.do_collect(gc_guard);
             true
         } else {
@@ -351,6 +907,15 @@ impl Collector {
         }
     }
 
+    // Like `check_then_collect`, but does nothing if a collection is already in progress
+
+    fn should_collect(&self) -> bool {
+        let current_data_count = self.tracked_data.data.len();
+        let current_handle_count = self.tracked_data.handles.len();
+        self.trigger
+            .should_collect(current_data_count, current_handle_count)
+    }
+
     pub fn collect(&self) {
         let gc_guard = self.gc_lock.lock();
         self.do_collect(gc_guard);
@@ -379,7 +944,8 @@ impl Collector {
         // Otherwise we'd see those handles as rooted and keep them around.
         // This makes a lot of sense in the background thread (since it's totally async),
         // but may slow direct calls to `collect`.
-        self.synchronize_destructors();
+        // (We never pump destructors here, since that'd run them on the wrong thread.)
+        self.synchronize_drop_thread();
 
         // The warrant system prevents us from scanning in-use data
         let warrants: SegQueue<GcExclusiveWarrant> = SegQueue::new();
@@ -399,6 +965,11 @@ impl Collector {
             }
 
             if let Some(warrant) = Lockout::get_exclusive_warrant(data.clone()) {
+                // If `destroy_now` got to this data first, there's nothing left to scan
+                if data.deallocated.load(Ordering::SeqCst) {
+                    return;
+                }
+
                 // Save that warrant so things can't shift around under us
                 warrants.push(warrant);
 
@@ -429,13 +1000,15 @@ impl Collector {
 
         // This step is dfs through the object graph (starting with the roots)
         // We mark each object we find
-        let dfs_stack = DynQueue::new(roots);
-        dfs_stack.into_par_iter().for_each(|(queue, handle)| {
+        traverse(roots, |handle, enqueue| {
             let data = &handle.underlying_data;
 
             // If this data is new, we don't want to `Scan` it, since we may not have its Lockout
             // Any handles inside this could not of been seen in step 1, so they'll be rooted anyway
-            if data.last_marked.load(Ordering::SeqCst) != 0 {
+            // (And if it's been destroyed by `destroy_now`, there's nothing left to scan)
+            if data.last_marked.load(Ordering::SeqCst) != 0
+                && !data.deallocated.load(Ordering::SeqCst)
+            {
                 // Essential note! All non-new non-warranted data is automatically marked
                 // Thus we will never accidentally scan non-warranted data here
                 let previous_mark = data.last_marked.swap(current_collection, Ordering::SeqCst);
@@ -453,7 +1026,7 @@ impl Collector {
                             .load(Ordering::SeqCst)
                             != current_collection
                         {
-                            queue.enqueue(h.handle_ref);
+                            enqueue(h.handle_ref);
                         }
                     });
                 }
@@ -463,33 +1036,32 @@ impl Collector {
         drop(warrants);
 
         // Now cleanup by removing all the data that is done for
-        par_retain(&self.tracked_data.data, |data, _| {
-            // Mark the new data as in use for now
-            // This stops us deallocating data that was allocated during collection
-            if data.last_marked.load(Ordering::SeqCst) == 0 {
-                data.last_marked.store(current_collection, Ordering::SeqCst);
-            }
+        par_retain(
+            &self.tracked_data.data,
+            |data, _| {
+                // Mark the new data as in use for now
+                // This stops us deallocating data that was allocated during collection
+                if data.last_marked.load(Ordering::SeqCst) == 0 {
+                    data.last_marked.store(current_collection, Ordering::SeqCst);
+                }
 
-            // If this is true, we just marked this data
-            if data.last_marked.load(Ordering::SeqCst) == current_collection {
-                // so retain it
-                true
-            } else {
+                // If this is true, we just marked this data (so retain it)
                 // Otherwise we didn't mark it and it should be deallocated
-                // eprintln!("deallocating {:?}", data_ptr);
-                // Send it to the drop thread to be dropped
-                let drop_msg = DropMessage::DataToDrop(data.clone());
+                data.last_marked.load(Ordering::SeqCst) == current_collection
+            },
+            |garbage| {
+                for data in &garbage {
+                    self.stopped_tracking(data);
+                }
+
+                // Send each shard's worth of garbage to the drop thread as a single batch
+                // Note: The destructor manages the `deallocated` flag so we can never access free'd data
+                let drop_msg = DropMessage::DataToDrop(garbage);
                 if let Err(e) = self.dropper.send_msg(drop_msg) {
                     error!("Error sending to drop thread {}", e);
                 }
-
-                // Note: It's okay to send all the data before we've removed it from the map
-                // The destructor manages the `destructed` flag so we can never access free'd data
-
-                // Don't retain this data
-                false
-            }
-        });
+            },
+        );
 
         // update the trigger based on the new baseline
         self.trigger
@@ -509,16 +1081,40 @@ impl Collector {
 pub static COLLECTOR: Lazy<Arc<Collector>> = Lazy::new(Collector::new);
 
 // Helper function! Lives here because it has nowhere else to go ;-;
-fn par_retain<K, V, F: Fn(&K, &V) -> bool>(map: &DashMap<K, V>, retain_fn: F)
+// Works like `retain`, but each shard's removed keys are handed to `removed_fn` in one batch
+fn par_retain<K, V, F, R>(map: &DashMap<K, V>, retain_fn: F, removed_fn: R)
 where
-    K: Eq + Hash + Send + Sync,
+    K: Clone + Eq + Hash + Send + Sync,
     V: Send + Sync,
-    F: Send + Sync,
+    F: Fn(&K, &V) -> bool + Send + Sync,
+    R: Fn(Vec<K>) + Send + Sync,
+{
+    map.shards().iter().par_bridge().for_each(|s| {
+        let mut removed = Vec::new();
+        s.write().retain(|k, v| {
+            let retain = retain_fn(k, v.get());
+            if !retain {
+                removed.push(k.clone());
+            }
+            retain
+        });
+
+        // The shard lock is released by this point, so `removed_fn` can take its time
+        if !removed.is_empty() {
+            removed_fn(removed);
+        }
+    });
+}
+
+// Calls `visit` on each root, then on everything `visit` enqueues (in parallel, if we can)
+fn traverse<F>(roots: Vec<Arc<GcHandle>>, visit: F)
+where
+    F: Fn(&Arc<GcHandle>, &mut dyn FnMut(Arc<GcHandle>)) + Send + Sync,
 {
-    map.shards()
-        .iter()
-        .par_bridge()
-        .for_each(|s| s.write().retain(|k, v| retain_fn(k, v.get())));
+    let dfs_stack = DynQueue::new(roots);
+    dfs_stack
+        .into_par_iter()
+        .for_each(|(queue, handle)| visit(&handle, &mut |h| queue.enqueue(h)));
 }
 
 #[cfg(test)]
@@ -541,7 +1137,11 @@ pub(crate) fn get_mock_handle() -> InternalGcRef {
             underlying_allocation: unsafe { GcAllocation::raw(Box::into_raw(mock_scannable)) },
             lockout: Lockout::new(),
             deallocated: AtomicBool::new(false),
+            initialized: AtomicBool::new(true),
             last_marked: AtomicU64::new(0),
+            owning_thread: None,
+            context: None,
+            handle_count: AtomicUsize::new(1),
         }),
         last_non_rooted: AtomicU64::new(0),
     }))
@@ -0,0 +1,180 @@
+use std::fmt::{self, Debug, Formatter};
+use std::time::{Duration, Instant};
+
+use parking_lot::{Condvar, Mutex};
+
+use crate::wrappers::{GcMutexGuard, GcPoisonError};
+use crate::{GcSafe, Scan, Scanner};
+
+/// A condition variable for use with `Gc<Mutex<T>>`, analogous to `std::sync::Condvar`.
+///
+/// Waiting releases both the mutex and the guard's hold on the `Gc` data (so the collector isn't
+/// held up), then re-acquires them in the usual order before returning. As with any condition
+/// variable, wakeups may be spurious, so check your condition in a loop (or use `wait_while`).
+///
+/// # Example
+/// ```
+/// use std::sync::{Arc, Mutex};
+/// use std::thread;
+///
+/// use shredder::{Gc, GcCondvar};
+///
+/// let queue = Gc::new(Mutex::new(Vec::new()));
+/// let condvar = Arc::new(GcCondvar::new());
+///
+/// let producer = {
+///     let queue = queue.clone();
+///     let condvar = condvar.clone();
+///     thread::spawn(move || {
+///         queue.lock().unwrap().push(7);
+///         condvar.notify_one();
+///     })
+/// };
+///
+/// let guard = condvar
+///     .wait_while(queue.lock().unwrap(), |q| q.is_empty())
+///     .unwrap();
+/// assert_eq!(*guard, vec![7]);
+/// # drop(guard);
+/// # producer.join().unwrap();
+/// ```
+pub struct GcCondvar {
+    /// incremented on every notification, so waiters can tell they've been notified
+    generation: Mutex<u64>,
+    condvar: Condvar,
+}
+
+/// Whether a `GcCondvar::wait_timeout` returned because the timeout elapsed
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct GcWaitTimeoutResult(bool);
+
+impl GcWaitTimeoutResult {
+    /// Returns `true` if the wait timed out (rather than being notified)
+    #[must_use]
+    pub fn timed_out(self) -> bool {
+        self.0
+    }
+}
+
+impl GcCondvar {
+    /// Create a new `GcCondvar`
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            generation: Mutex::new(0),
+            condvar: Condvar::new(),
+        }
+    }
+
+    /// Release the lock held by `guard`, and block until this condvar is notified. Then lock the
+    /// same `Gc<Mutex<T>>` again.
+    ///
+    /// # Errors
+    /// Returns a `GcPoisonError` if the mutex was poisoned when re-locking it.
+    /// You may use `into_inner` in order to recover the guard from that error.
+    pub fn wait<'a, T: Scan + 'static>(
+        &self,
+        guard: GcMutexGuard<'a, T>,
+    ) -> Result<GcMutexGuard<'a, T>, GcPoisonError<GcMutexGuard<'a, T>>> {
+        let gc = GcMutexGuard::gc_of(&guard);
+
+        // We take the generation lock before unlocking the mutex, so a notification can't slip in
+        // between unlocking and waiting
+        let mut generation = self.generation.lock();
+        let start = *generation;
+        drop(guard);
+        while *generation == start {
+            self.condvar.wait(&mut generation);
+        }
+        drop(generation);
+
+        gc.lock()
+    }
+
+    /// Like `wait`, but gives up after `timeout` has passed
+    ///
+    /// # Errors
+    /// Returns a `GcPoisonError` if the mutex was poisoned when re-locking it.
+    /// You may use `into_inner` in order to recover the guard from that error.
+    #[allow(clippy::type_complexity)]
+    pub fn wait_timeout<'a, T: Scan + 'static>(
+        &self,
+        guard: GcMutexGuard<'a, T>,
+        timeout: Duration,
+    ) -> Result<
+        (GcMutexGuard<'a, T>, GcWaitTimeoutResult),
+        GcPoisonError<(GcMutexGuard<'a, T>, GcWaitTimeoutResult)>,
+    > {
+        let gc = GcMutexGuard::gc_of(&guard);
+
+        let deadline = Instant::now() + timeout;
+        let mut generation = self.generation.lock();
+        let start = *generation;
+        drop(guard);
+        let mut timed_out = false;
+        while *generation == start && !timed_out {
+            timed_out = self
+                .condvar
+                .wait_until(&mut generation, deadline)
+                .timed_out();
+        }
+        drop(generation);
+
+        let res = GcWaitTimeoutResult(timed_out);
+        match gc.lock() {
+            Ok(guard) => Ok((guard, res)),
+            Err(e) => Err(GcPoisonError {
+                guard: (e.into_inner(), res),
+            }),
+        }
+    }
+
+    /// Wait on this condvar until `condition` returns `false`. (`condition` is checked first,
+    /// so this doesn't wait at all if it's already `false`.)
+    ///
+    /// # Errors
+    /// Returns a `GcPoisonError` if the mutex was poisoned when re-locking it.
+    /// You may use `into_inner` in order to recover the guard from that error.
+    pub fn wait_while<'a, T: Scan + 'static, F: FnMut(&mut T) -> bool>(
+        &self,
+        mut guard: GcMutexGuard<'a, T>,
+        mut condition: F,
+    ) -> Result<GcMutexGuard<'a, T>, GcPoisonError<GcMutexGuard<'a, T>>> {
+        while condition(&mut *guard) {
+            guard = self.wait(guard)?;
+        }
+
+        Ok(guard)
+    }
+
+    /// Wake up one thread waiting on this condvar
+    pub fn notify_one(&self) {
+        *self.generation.lock() += 1;
+        self.condvar.notify_one();
+    }
+
+    /// Wake up every thread waiting on this condvar
+    pub fn notify_all(&self) {
+        *self.generation.lock() += 1;
+        self.condvar.notify_all();
+    }
+}
+
+impl Default for GcCondvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for GcCondvar {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcCondvar").finish_non_exhaustive()
+    }
+}
+
+// A `GcCondvar` doesn't contain any `Gc`s, so it can live inside garbage collected data
+unsafe impl Scan for GcCondvar {
+    #[inline]
+    fn scan(&self, _: &mut Scanner<'_>) {}
+}
+unsafe impl GcSafe for GcCondvar {}
@@ -0,0 +1,209 @@
+use std::cell::Cell;
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use dashmap::DashMap;
+
+use crate::GcError;
+
+/// Identifies who an allocation was made on behalf of (for instance, one tenant's script), so
+/// memory can be attributed with `context_stats`.
+///
+/// Allocations record the context their thread is in when they're made. Enter a context with
+/// `with_gc_context`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct AllocationContext(pub u64);
+
+/// How much one `AllocationContext` has allocated, as reported by `context_stats`
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ContextStats {
+    /// how many allocations made in this context are still tracked
+    pub objects: usize,
+    /// the payload bytes of those allocations (see `heap_bytes`)
+    pub bytes: usize,
+    /// the limit set by `set_context_quota`, if any
+    pub quota: Option<usize>,
+    /// how many allocations took `bytes` past `quota` (these still succeed, unless made with
+    /// `Gc::try_new`)
+    pub over_quota_allocations: usize,
+}
+
+thread_local! {
+    static CURRENT_CONTEXT: Cell<Option<AllocationContext>> = const { Cell::new(None) };
+}
+
+/// The context allocations on this thread should be attributed to
+pub(crate) fn current_context() -> Option<AllocationContext> {
+    CURRENT_CONTEXT.with(Cell::get)
+}
+
+/// Restores the previous context, even if `with_gc_context`'s closure panics
+struct ContextGuard {
+    previous: Option<AllocationContext>,
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        CURRENT_CONTEXT.with(|current| current.set(self.previous));
+    }
+}
+
+/// Run `f`, attributing every allocation it makes on this thread to `context`.
+///
+/// Contexts nest: inside `f`, another `with_gc_context` call takes over until it returns. Work
+/// `f` hands off to other threads isn't attributed to `context` (unless those threads enter it too).
+///
+/// # Example
+/// ```
+/// use shredder::{context_stats, with_gc_context, AllocationContext, Gc};
+///
+/// let tenant = AllocationContext(7);
+/// let data = with_gc_context(tenant, || Gc::new(String::from("tenant data")));
+///
+/// let stats = context_stats(tenant);
+/// assert_eq!(stats.objects, 1);
+/// assert!(stats.bytes > 0);
+/// ```
+pub fn with_gc_context<R, F: FnOnce() -> R>(context: AllocationContext, f: F) -> R {
+    let previous = CURRENT_CONTEXT.with(|current| current.replace(Some(context)));
+    let _guard = ContextGuard { previous };
+    f()
+}
+
+/// The running totals for one context
+#[derive(Debug)]
+struct ContextUsage {
+    objects: AtomicUsize,
+    bytes: AtomicUsize,
+    /// `usize::MAX` means there's no quota
+    quota: AtomicUsize,
+    over_quota_allocations: AtomicUsize,
+}
+
+impl Default for ContextUsage {
+    fn default() -> Self {
+        Self {
+            objects: AtomicUsize::new(0),
+            bytes: AtomicUsize::new(0),
+            quota: AtomicUsize::new(usize::MAX),
+            over_quota_allocations: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl ContextUsage {
+    fn stats(&self) -> ContextStats {
+        let quota = self.quota.load(Ordering::SeqCst);
+        ContextStats {
+            objects: self.objects.load(Ordering::SeqCst),
+            bytes: self.bytes.load(Ordering::SeqCst),
+            quota: if quota == usize::MAX {
+                None
+            } else {
+                Some(quota)
+            },
+            over_quota_allocations: self.over_quota_allocations.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// Tracks usage for every context that has allocated (or had a quota set)
+#[derive(Debug, Default)]
+pub(crate) struct ContextAccounting {
+    usage: DashMap<AllocationContext, ContextUsage>,
+}
+
+impl ContextAccounting {
+    pub fn record_allocation(&self, context: AllocationContext, bytes: usize) {
+        let usage = self.usage.entry(context).or_default();
+        usage.objects.fetch_add(1, Ordering::SeqCst);
+        let total = usage.bytes.fetch_add(bytes, Ordering::SeqCst) + bytes;
+        if total > usage.quota.load(Ordering::SeqCst) {
+            usage.over_quota_allocations.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    pub fn record_deallocation(&self, context: AllocationContext, bytes: usize) {
+        if let Some(usage) = self.usage.get(&context) {
+            usage.objects.fetch_sub(1, Ordering::SeqCst);
+            usage.bytes.fetch_sub(bytes, Ordering::SeqCst);
+        }
+    }
+
+    /// Reserve `bytes` in `context` for an allocation, or fail if that would go past its quota.
+    /// (The check and the reservation are one atomic step, so racing allocations can't both fit.)
+    pub fn try_reserve(
+        &self,
+        context: AllocationContext,
+        bytes: usize,
+    ) -> Result<Reservation<'_>, GcError> {
+        let usage = self.usage.entry(context).or_default();
+        let quota = usage.quota.load(Ordering::SeqCst);
+        usage
+            .bytes
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |current| {
+                current.checked_add(bytes).filter(|total| *total <= quota)
+            })
+            .map_err(|_| GcError::QuotaExceeded)?;
+
+        Ok(Reservation {
+            accounting: self,
+            context,
+            bytes,
+        })
+    }
+
+    fn release(&self, context: AllocationContext, bytes: usize) {
+        if let Some(usage) = self.usage.get(&context) {
+            usage.bytes.fetch_sub(bytes, Ordering::SeqCst);
+        }
+    }
+
+    pub fn set_quota(&self, context: AllocationContext, quota: Option<usize>) {
+        let usage = self.usage.entry(context).or_default();
+        usage
+            .quota
+            .store(quota.unwrap_or(usize::MAX), Ordering::SeqCst);
+    }
+
+    pub fn stats(&self, context: AllocationContext) -> ContextStats {
+        self.usage
+            .get(&context)
+            .map_or_else(ContextStats::default, |usage| usage.stats())
+    }
+
+    pub fn all_stats(&self) -> Vec<(AllocationContext, ContextStats)> {
+        self.usage
+            .iter()
+            .map(|entry| (*entry.key(), entry.value().stats()))
+            .collect()
+    }
+}
+
+/// Bytes set aside in a context by `ContextAccounting::try_reserve`. Unless an allocation claims
+/// them with `commit`, they're released when this is dropped.
+pub(crate) struct Reservation<'a> {
+    accounting: &'a ContextAccounting,
+    context: AllocationContext,
+    bytes: usize,
+}
+
+impl Reservation<'_> {
+    pub fn context(&self) -> AllocationContext {
+        self.context
+    }
+
+    /// Record an allocation that uses the reserved bytes
+    pub fn commit(self) {
+        if let Some(usage) = self.accounting.usage.get(&self.context) {
+            usage.objects.fetch_add(1, Ordering::SeqCst);
+        }
+        mem::forget(self);
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        self.accounting.release(self.context, self.bytes);
+    }
+}
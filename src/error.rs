@@ -0,0 +1,32 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+/// An error from one of `Gc`'s fallible methods, like `checked_get`, `get_timeout` or `try_new`
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum GcError {
+    /// The collector was scanning this data, and hadn't finished by the time we gave up.
+    /// (`get_timeout(Duration::ZERO)` gives up right away.)
+    Timeout,
+    /// The data has already been deallocated. This can happen when accessing a `Gc` from a
+    /// destructor, or after its `Scope` has ended.
+    Deallocated,
+    /// The data hasn't been constructed yet (the `Gc` was accessed inside `Gc::new_cyclic`)
+    Uninitialized,
+    /// Allocating would take the current `AllocationContext` past its quota (see
+    /// `set_context_quota`)
+    QuotaExceeded,
+}
+
+impl Display for GcError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout => write!(f, "timed out waiting for the collector to finish scanning"),
+            Self::Deallocated => write!(f, "the data in this Gc has already been deallocated"),
+            Self::Uninitialized => write!(f, "the data in this Gc hasn't been constructed yet"),
+            Self::QuotaExceeded => write!(f, "this allocation would exceed its context's quota"),
+        }
+    }
+}
+
+impl Error for GcError {}
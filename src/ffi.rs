@@ -0,0 +1,270 @@
+//! A C interface to the collector, for hosts that manage `shredder` objects from C or C++.
+//!
+//! Objects cross the boundary as opaque `ShredderHandle` pointers. Each handle is a root, just like
+//! a `Gc` on the stack, until it's released with `shredder_handle_drop`. The host can also register
+//! its own objects with `shredder_register_foreign`, and let them reference other objects with
+//! `shredder_foreign_add_reference` (so cycles through foreign objects can be collected).
+//!
+//! The declarations for C are in `include/shredder.h`. To export these symbols, link this crate
+//! (with the `ffi` feature) into a `staticlib` or `cdylib`.
+
+use std::any::Any;
+use std::ffi::c_void;
+use std::fmt::{self, Debug, Formatter};
+use std::ptr;
+
+use parking_lot::Mutex;
+
+use crate::{
+    collect, number_of_active_handles, number_of_tracked_allocations, synchronize_destructors, Gc,
+    GcSafe, Scan, Scanner,
+};
+
+/// A destructor for a foreign object, called with its `data` pointer
+pub type ShredderDestroyFn = unsafe extern "C" fn(data: *mut c_void);
+
+/// Lets a handle hold onto any `Gc`, while forgetting its type
+trait ErasedGc: Send + Sync {
+    fn clone_gc(&self) -> Box<dyn ErasedGc>;
+    fn scan_gc(&self, scanner: &mut Scanner<'_>);
+    fn id(&self) -> u64;
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Scan + Send + Sync + ?Sized + 'static> ErasedGc for Gc<T> {
+    fn clone_gc(&self) -> Box<dyn ErasedGc> {
+        Box::new(self.clone())
+    }
+
+    fn scan_gc(&self, scanner: &mut Scanner<'_>) {
+        scanner.scan(self);
+    }
+
+    fn id(&self) -> u64 {
+        Gc::id(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// An opaque, owning handle to a garbage collected object, for passing to C.
+///
+/// # Example
+/// ```
+/// use shredder::ffi::{shredder_handle_drop, ShredderHandle};
+/// use shredder::Gc;
+///
+/// let handle: *mut ShredderHandle = ShredderHandle::new(Gc::new(7_u32)).into_raw();
+/// // <hand `handle` to C, which calls back into Rust with it>
+/// let gc = unsafe { &*handle }.downcast_ref::<u32>().unwrap();
+/// assert_eq!(*gc.get(), 7);
+/// unsafe { shredder_handle_drop(handle) };
+/// ```
+pub struct ShredderHandle {
+    gc: Box<dyn ErasedGc>,
+}
+
+impl ShredderHandle {
+    /// Wrap a `Gc` in a handle
+    #[must_use]
+    pub fn new<T: Scan + Send + Sync + ?Sized + 'static>(gc: Gc<T>) -> Self {
+        Self { gc: Box::new(gc) }
+    }
+
+    /// Move this handle to the heap, returning a pointer that can be given to C
+    #[must_use]
+    pub fn into_raw(self) -> *mut Self {
+        Box::into_raw(Box::new(self))
+    }
+
+    /// Take back ownership of a handle from `into_raw` (or one of the `shredder_*` functions)
+    ///
+    /// # Safety
+    /// `ptr` must have come from `into_raw`, and not have been dropped or reclaimed already
+    pub unsafe fn from_raw(ptr: *mut Self) -> Self {
+        *Box::from_raw(ptr)
+    }
+
+    /// Get the `Gc` in this handle, if it's a `Gc<T>`
+    #[must_use]
+    pub fn downcast_ref<T: Scan + Send + Sync + ?Sized + 'static>(&self) -> Option<&Gc<T>> {
+        self.gc.as_any().downcast_ref()
+    }
+
+    /// The id of the object this handle points at (see `Gc::id`)
+    #[must_use]
+    pub fn id(&self) -> u64 {
+        self.gc.id()
+    }
+
+    fn foreign(&self) -> Option<&Gc<ForeignObject>> {
+        self.downcast_ref()
+    }
+}
+
+impl Clone for ShredderHandle {
+    fn clone(&self) -> Self {
+        Self {
+            gc: self.gc.clone_gc(),
+        }
+    }
+}
+
+impl Debug for ShredderHandle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShredderHandle")
+            .field("id", &self.id())
+            .finish()
+    }
+}
+
+unsafe impl Scan for ShredderHandle {
+    #[inline]
+    fn scan(&self, scanner: &mut Scanner<'_>) {
+        self.gc.scan_gc(scanner);
+    }
+}
+unsafe impl GcSafe for ShredderHandle {}
+
+/// An object owned by the host, whose lifetime is managed by the collector
+struct ForeignObject {
+    data: *mut c_void,
+    destroy: Option<ShredderDestroyFn>,
+    /// the objects this one keeps alive
+    references: Mutex<Vec<ShredderHandle>>,
+}
+
+// Safety: The host promises (in `shredder_register_foreign`) that `data` can be used and destroyed
+// from any thread
+unsafe impl Send for ForeignObject {}
+unsafe impl Sync for ForeignObject {}
+
+impl Drop for ForeignObject {
+    fn drop(&mut self) {
+        if let Some(destroy) = self.destroy {
+            unsafe { destroy(self.data) };
+        }
+    }
+}
+
+unsafe impl Scan for ForeignObject {
+    #[inline]
+    fn scan(&self, scanner: &mut Scanner<'_>) {
+        scanner.scan(&self.references);
+    }
+}
+unsafe impl GcSafe for ForeignObject {}
+
+/// Make a new handle to the same object as `handle`. Returns null if `handle` is null.
+///
+/// # Safety
+/// `handle` must be null or a live handle
+#[no_mangle]
+pub unsafe extern "C" fn shredder_handle_clone(
+    handle: *const ShredderHandle,
+) -> *mut ShredderHandle {
+    match handle.as_ref() {
+        Some(handle) => handle.clone().into_raw(),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Release a handle. The object is no longer rooted by it, so it may be collected. Does nothing if
+/// `handle` is null.
+///
+/// # Safety
+/// `handle` must be null or a live handle, and must not be used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn shredder_handle_drop(handle: *mut ShredderHandle) {
+    if !handle.is_null() {
+        drop(ShredderHandle::from_raw(handle));
+    }
+}
+
+/// Returns the id of the object `handle` points at (see `Gc::id`). Two handles point at the same
+/// object exactly when their ids are equal.
+///
+/// # Safety
+/// `handle` must be a live handle
+#[no_mangle]
+pub unsafe extern "C" fn shredder_handle_id(handle: *const ShredderHandle) -> u64 {
+    (*handle).id()
+}
+
+/// Run a collection on the calling thread (see `collect`)
+#[no_mangle]
+pub extern "C" fn shredder_collect() {
+    collect();
+}
+
+/// Wait for the destructors of all garbage found so far to run (see `synchronize_destructors`)
+#[no_mangle]
+pub extern "C" fn shredder_synchronize_destructors() {
+    synchronize_destructors();
+}
+
+/// See `number_of_tracked_allocations`
+#[no_mangle]
+pub extern "C" fn shredder_number_of_tracked_allocations() -> usize {
+    number_of_tracked_allocations()
+}
+
+/// See `number_of_active_handles`
+#[no_mangle]
+pub extern "C" fn shredder_number_of_active_handles() -> usize {
+    number_of_active_handles()
+}
+
+/// Hand a host object over to the collector, returning a handle that roots it. Once the object
+/// is garbage, `destroy` (if not null) is called with `data`.
+///
+/// # Safety
+/// `data` must be safe to use, and `destroy` safe to call, from any thread. (Destructors run on a
+/// background thread by default.)
+#[no_mangle]
+pub unsafe extern "C" fn shredder_register_foreign(
+    data: *mut c_void,
+    destroy: Option<ShredderDestroyFn>,
+) -> *mut ShredderHandle {
+    let object = ForeignObject {
+        data,
+        destroy,
+        references: Mutex::new(Vec::new()),
+    };
+    ShredderHandle::new(Gc::new(object)).into_raw()
+}
+
+/// Returns the `data` pointer a foreign object was registered with, or null if `handle` is null or
+/// not a foreign object
+///
+/// # Safety
+/// `handle` must be null or a live handle
+#[no_mangle]
+pub unsafe extern "C" fn shredder_foreign_data(handle: *const ShredderHandle) -> *mut c_void {
+    handle
+        .as_ref()
+        .and_then(ShredderHandle::foreign)
+        .map_or(ptr::null_mut(), |foreign| foreign.get().data)
+}
+
+/// Make the foreign object `owner` keep `target` alive. Unlike holding a handle, this isn't a root,
+/// so a cycle of references is still collected. Returns `false` (doing nothing) if `owner` isn't a
+/// foreign object.
+///
+/// # Safety
+/// `owner` and `target` must be live handles
+#[no_mangle]
+pub unsafe extern "C" fn shredder_foreign_add_reference(
+    owner: *const ShredderHandle,
+    target: *const ShredderHandle,
+) -> bool {
+    match (*owner).foreign() {
+        Some(foreign) => {
+            foreign.get().references.lock().push((*target).clone());
+            true
+        }
+        None => false,
+    }
+}
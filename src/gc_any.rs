@@ -0,0 +1,13 @@
+use std::any::Any;
+
+use crate::Scan;
+
+/// A `Scan` type that can be downcast. This lets data of different types live behind
+/// `Gc<dyn GcAny>`, and be recovered with `Gc::downcast`.
+///
+/// This is implemented for every `'static` type that implements `Scan`. Make a `Gc<dyn GcAny>` with
+/// `Gc::into_any` (or `Gc::from_box`). A `Gc<dyn GcAny>` can't be sent between threads, so for
+/// thread safe data there's also `Gc<dyn GcAny + Send + Sync>`, made with `Gc::into_any_sync`.
+pub trait GcAny: Scan + Any {}
+
+impl<T: Scan + Any> GcAny for T {}
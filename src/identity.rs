@@ -0,0 +1,93 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+use crate::{Gc, GcSafe, Scan, Scanner};
+
+/// A wrapper that compares, hashes, and orders a `Gc` by identity (see `Gc::id`), rather than by
+/// the data inside it.
+///
+/// `Gc`'s own `Eq`/`Hash`/`Ord` impls access the data, which blocks while it's being scanned and
+/// never terminates for cyclic data. `ByIdentity` doesn't touch the data at all, so it's a cheap
+/// key for a `HashSet` or `HashMap` (for instance, a "visited" set in a graph traversal).
+///
+/// # Example
+/// ```
+/// use std::collections::HashSet;
+///
+/// use shredder::{ByIdentity, Gc};
+///
+/// let a = Gc::new(1);
+/// let b = Gc::new(1);
+///
+/// let mut visited = HashSet::new();
+/// assert!(visited.insert(ByIdentity(a.clone())));
+/// assert!(visited.insert(ByIdentity(b)));
+/// assert!(!visited.insert(ByIdentity(a)));
+/// assert_eq!(visited.len(), 2);
+/// ```
+#[derive(Clone, Copy, Default)]
+pub struct ByIdentity<P>(pub P);
+
+impl<P> ByIdentity<P> {
+    /// Unwrap the pointer inside
+    pub fn into_inner(self) -> P {
+        self.0
+    }
+}
+
+impl<P> Deref for ByIdentity<P> {
+    type Target = P;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T: Scan + ?Sized> From<Gc<T>> for ByIdentity<Gc<T>> {
+    fn from(gc: Gc<T>) -> Self {
+        Self(gc)
+    }
+}
+
+impl<T: Scan + ?Sized> PartialEq for ByIdentity<Gc<T>> {
+    fn eq(&self, other: &Self) -> bool {
+        Gc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<T: Scan + ?Sized> Eq for ByIdentity<Gc<T>> {}
+
+impl<T: Scan + ?Sized> Hash for ByIdentity<Gc<T>> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.id().hash(state);
+    }
+}
+
+impl<T: Scan + ?Sized> PartialOrd for ByIdentity<Gc<T>> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Orders by `Gc::id`, which is roughly allocation order
+impl<T: Scan + ?Sized> Ord for ByIdentity<Gc<T>> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.id().cmp(&other.0.id())
+    }
+}
+
+impl<T: Scan + ?Sized> Debug for ByIdentity<Gc<T>> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ByIdentity").field(&self.0.id()).finish()
+    }
+}
+
+unsafe impl<P: Scan> Scan for ByIdentity<P> {
+    #[inline]
+    fn scan(&self, scanner: &mut Scanner<'_>) {
+        scanner.scan(&self.0);
+    }
+}
+unsafe impl<P: GcSafe> GcSafe for ByIdentity<P> {}
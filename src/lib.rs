@@ -49,10 +49,20 @@ extern crate log;
 #[macro_use]
 extern crate rental;
 
+mod async_mutex;
 mod collector;
+mod condvar;
+mod context;
+mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 mod finalize;
+mod gc_any;
+mod identity;
+mod local;
 mod lockout;
 mod scan;
+mod scope;
 mod smart_ptr;
 /// Helpful wrappers used for convenience methods
 pub mod wrappers;
@@ -62,9 +72,19 @@ use std::sync::{Mutex, RwLock};
 
 use collector::COLLECTOR;
 
+pub use async_mutex::GcAsyncMutex;
+pub use collector::PoisonPolicy;
+pub use collector::{DestructorBacklogPolicy, DestructorMode, HeapBytes};
+pub use condvar::{GcCondvar, GcWaitTimeoutResult};
+pub use context::{with_gc_context, AllocationContext, ContextStats};
+pub use error::GcError;
 pub use finalize::Finalize;
+pub use gc_any::GcAny;
+pub use identity::ByIdentity;
+pub use local::LocalGc;
 pub use scan::{GcSafe, GcSafeWrapper, RMut, Scan, Scanner, R};
-pub use smart_ptr::{Gc, GcGuard};
+pub use scope::Scope;
+pub use smart_ptr::{Gc, GcGuard, GcGuardMut, OwnedGcGuard};
 
 // Re-export the Scan derive
 pub use shredder_derive::Scan;
@@ -84,6 +104,16 @@ pub type GMutex<T> = Gc<Mutex<T>>;
 /// `Gc`s.
 pub type GRwLock<T> = Gc<RwLock<T>>;
 
+/// A convenient alias for `Gc<parking_lot::Mutex<T>>`.
+/// Note that `Gc<parking_lot::Mutex<T>>` has additional specialized methods for working with
+/// `parking_lot::Mutex`s inside `Gc`s.
+pub type GParkingLotMutex<T> = Gc<parking_lot::Mutex<T>>;
+
+/// A convenient alias for `Gc<parking_lot::RwLock<T>>`.
+/// Note that `Gc<parking_lot::RwLock<T>>` has additional specialized methods (including upgradable
+/// reads) for working with `parking_lot::RwLock`s inside `Gc`s.
+pub type GParkingLotRwLock<T> = Gc<parking_lot::RwLock<T>>;
+
 /// Returns how many underlying allocations are currently allocated.
 ///
 /// # Example
@@ -112,6 +142,117 @@ pub fn number_of_active_handles() -> usize {
     COLLECTOR.handle_count()
 }
 
+/// Returns how many bytes of heap memory the collector is using: the data in each `Gc`
+/// (`payload`), plus an estimate of the collector's own bookkeeping (`metadata`).
+///
+/// The metadata covers the per-allocation and per-`Gc` records, and the capacity reserved by the
+/// maps holding them. (Memory owned by the data itself, like a `Vec`'s buffer, isn't counted. The
+/// exception is `Gc::from_box`, where the `Box`'s buffer is the data, so it's counted as payload.)
+///
+/// # Example
+/// ```
+/// use shredder::{heap_bytes, Gc};
+///
+/// let data: Gc<[u8]> = Gc::from(vec![0_u8; 1024]);
+/// let bytes = heap_bytes();
+/// assert!(bytes.payload >= 1024);
+/// assert!(bytes.metadata > 0);
+/// assert_eq!(bytes.total(), bytes.payload + bytes.metadata);
+/// ```
+#[must_use]
+pub fn heap_bytes() -> HeapBytes {
+    COLLECTOR.heap_bytes()
+}
+
+/// Returns how much the allocations made in `context` (see `with_gc_context`) are using.
+///
+/// Allocations are counted until they're freed. So garbage is included until a collection finds
+/// it, and right after `collect` these are the live objects and bytes.
+///
+/// # Example
+/// ```
+/// use shredder::{collect, context_stats, with_gc_context, AllocationContext, Gc};
+///
+/// let tenant = AllocationContext(1);
+/// let kept = with_gc_context(tenant, || {
+///     let _temporary = Gc::new(1_u64);
+///     Gc::new(2_u64)
+/// });
+///
+/// collect();
+/// assert_eq!(context_stats(tenant).objects, 1);
+/// ```
+#[must_use]
+pub fn context_stats(context: AllocationContext) -> ContextStats {
+    COLLECTOR.context_stats(context)
+}
+
+/// Returns `context_stats` for every context that has allocated (or had a quota set).
+///
+/// # Example
+/// ```
+/// use shredder::{all_context_stats, with_gc_context, AllocationContext, Gc};
+///
+/// let tenant = AllocationContext(2);
+/// let data = with_gc_context(tenant, || Gc::new(0_u8));
+///
+/// assert!(all_context_stats().iter().any(|(context, _)| *context == tenant));
+/// ```
+#[must_use]
+pub fn all_context_stats() -> Vec<(AllocationContext, ContextStats)> {
+    COLLECTOR.all_context_stats()
+}
+
+/// Limits the payload bytes allocations in `context` may use (or removes the limit with `None`).
+///
+/// Going past the quota doesn't stop `Gc::new`, but is counted in
+/// `ContextStats::over_quota_allocations`. Allocate with `Gc::try_new` to fail instead.
+///
+/// # Example
+/// ```
+/// use shredder::{set_context_quota, with_gc_context, AllocationContext, Gc, GcError};
+///
+/// let tenant = AllocationContext(3);
+/// set_context_quota(tenant, Some(16));
+///
+/// with_gc_context(tenant, || {
+///     let a = Gc::try_new(1_u64).unwrap();
+///     let b = Gc::try_new(2_u64).unwrap();
+///     assert_eq!(Gc::try_new(3_u64).unwrap_err(), GcError::QuotaExceeded);
+/// });
+/// ```
+pub fn set_context_quota(context: AllocationContext, quota: Option<usize>) {
+    COLLECTOR.set_context_quota(context, quota);
+}
+
+/// Returns a new `Gc` for each object of type `T` the collector is currently tracking. This is
+/// meant for checking invariants (in tests, or from admin tooling), not for normal data access.
+///
+/// Only data allocated directly as a `T` is found. (So data from `Gc::from_box`, or allocated
+/// without a `'static` type, like `Gc::new_with_finalizer`, isn't included.) The returned `Gc`s
+/// are roots, so anything found stays alive until they're dropped. Data that's being mutated
+/// through `Gc::get_mut` (or unwrapped by `Gc::try_unwrap`) at that moment is skipped, since a new
+/// `Gc` would alias it.
+///
+/// # Example
+/// ```
+/// use shredder::{live_objects, Gc, Scan};
+///
+/// #[derive(Scan)]
+/// struct Session(u32);
+///
+/// let a = Gc::new(Session(1));
+/// let b = Gc::new(Session(2));
+///
+/// let mut ids: Vec<u32> = live_objects::<Session>().iter().map(|s| s.get().0).collect();
+/// ids.sort_unstable();
+/// assert_eq!(ids, [1, 2]);
+/// ```
+#[must_use]
+pub fn live_objects<T: Scan + Send + Sync + 'static>() -> Vec<Gc<T>> {
+    Gc::live_objects()
+}
+
 /// Sets the percent more data that'll trigger collection.
 ///
 /// `shredders` collection automatically triggers when:
@@ -137,6 +278,70 @@ pub fn set_gc_trigger_percent(percent: f32) {
     COLLECTOR.set_gc_trigger_percent(percent)
 }
 
+/// Returns how many `LocalGc` allocations the current thread's local heap is tracking.
+///
+/// # Example
+/// ```
+/// use shredder::{number_of_local_allocations, LocalGc};
+///
+/// let data = LocalGc::new(128);
+/// assert_eq!(number_of_local_allocations(), 1);
+/// ```
+#[must_use]
+pub fn number_of_local_allocations() -> usize {
+    local::local_allocation_count()
+}
+
+/// Runs a collection of the current thread's local heap (which only holds `LocalGc` data).
+///
+/// This happens synchronously, so all unreachable `LocalGc` data will have been dropped by the
+/// time this returns. It doesn't interact with the global collector at all.
+///
+/// # Example
+/// ```
+/// use shredder::{collect_local, number_of_local_allocations, LocalGc};
+///
+/// drop(LocalGc::new(128));
+/// collect_local();
+/// assert_eq!(number_of_local_allocations(), 0);
+/// ```
+pub fn collect_local() {
+    local::collect_local();
+}
+
+/// Returns how many pieces of garbage are waiting for their destructors to run.
+///
+/// # Example
+/// ```
+/// use shredder::{collect, number_of_pending_destructors, synchronize_destructors, Gc};
+///
+/// drop(Gc::new(128));
+/// collect();
+/// synchronize_destructors();
+/// assert_eq!(number_of_pending_destructors(), 0);
+/// ```
+#[must_use]
+pub fn number_of_pending_destructors() -> usize {
+    COLLECTOR.pending_destructor_count()
+}
+
+/// Sets what allocating threads should do when the background thread falls behind on destructors.
+///
+/// By default garbage waits for the background thread however long it takes, so if you allocate
+/// faster than destructors can run, memory won't be returned. With `Help` allocating threads run
+/// pending destructors themselves once the backlog is too large, and with `Block` they wait for the
+/// background thread to catch up. (Destructors never help or block, even if they allocate.)
+///
+/// # Example
+/// ```
+/// use shredder::{set_destructor_backlog_policy, DestructorBacklogPolicy};
+/// // Once 4096 pieces of garbage are waiting, allocating threads will help drop them
+/// set_destructor_backlog_policy(DestructorBacklogPolicy::Help { limit: 4096 });
+/// ```
+pub fn set_destructor_backlog_policy(policy: DestructorBacklogPolicy) {
+    COLLECTOR.set_destructor_backlog_policy(policy);
+}
+
 /// A function for manually running a collection, ignoring the heuristic that governs normal
 /// garbage collector operations.
 ///
@@ -159,6 +364,9 @@ pub fn collect() {
 ///
 /// This method is most useful for testing, as well as cleaning up at the termination of your
 /// program.
+///
+/// In `DestructorMode::CallerPumped` mode, this also runs all pending destructors on the current
+/// thread. (So only call it from a thread you're happy to run destructors on!)
 /// # Example
 /// ```
 /// use shredder::{collect, synchronize_destructors};
@@ -174,6 +382,133 @@ pub fn synchronize_destructors() {
     COLLECTOR.synchronize_destructors()
 }
 
+/// Sets what happens when you lock a poisoned `Gc<Mutex<T>>` or `Gc<RwLock<T>>`.
+///
+/// By default you get a `GcPoisonError` (which you can still recover the guard from). With
+/// `PoisonPolicy::Ignore`, poisoning is ignored and you get the guard directly. The collector scans
+/// through poisoned locks regardless of this policy.
+///
+/// # Example
+/// ```
+/// use std::sync::Mutex;
+/// use std::thread;
+///
+/// use shredder::{set_poison_policy, Gc, PoisonPolicy};
+///
+/// let data = Gc::new(Mutex::new(0));
+/// let data_clone = data.clone();
+/// let _ = thread::spawn(move || {
+///     let _guard = data_clone.lock().unwrap();
+///     panic!("poison the mutex");
+/// })
+/// .join();
+/// assert!(data.lock().is_err());
+///
+/// set_poison_policy(PoisonPolicy::Ignore);
+/// assert!(data.lock().is_ok());
+/// # set_poison_policy(PoisonPolicy::Report);
+/// ```
+pub fn set_poison_policy(policy: PoisonPolicy) {
+    COLLECTOR.set_poison_policy(policy);
+}
+
+/// Sets where destructors for garbage are run.
+///
+/// By default, destructors run on a background thread. In `DestructorMode::CallerPumped` mode,
+/// garbage is queued up instead, and destructors only run when you call `run_pending_destructors`
+/// (or `synchronize_destructors`). This is useful when destructors must run on a specific thread,
+/// like a UI thread. Switching back to `DestructorMode::Background` hands any queued garbage to the
+/// background thread.
+///
+/// # Example
+/// ```
+/// use shredder::{set_destructor_mode, DestructorMode};
+/// set_destructor_mode(DestructorMode::CallerPumped);
+/// // <SNIP>
+/// set_destructor_mode(DestructorMode::Background);
+/// ```
+pub fn set_destructor_mode(mode: DestructorMode) {
+    COLLECTOR.set_destructor_mode(mode);
+}
+
+/// Runs up to `max` pending destructors on the current thread, returning how many were run.
+///
+/// This is only useful in `DestructorMode::CallerPumped` mode, where you'd usually call this from
+/// your event loop. Calling it from inside a destructor does nothing.
+///
+/// # Example
+/// ```
+/// use shredder::{collect, run_pending_destructors, set_destructor_mode, DestructorMode, Gc};
+///
+/// set_destructor_mode(DestructorMode::CallerPumped);
+/// drop(Gc::new(128));
+/// collect();
+/// // Later, in our event loop...
+/// while run_pending_destructors(32) > 0 {}
+/// # set_destructor_mode(DestructorMode::Background);
+/// ```
+pub fn run_pending_destructors(max: usize) -> usize {
+    COLLECTOR.run_pending_destructors(max)
+}
+
+/// Runs the destructors for all thread-affine garbage owned by the current thread, returning how
+/// many were run.
+///
+/// Data allocated with `Gc::new_thread_affine` is never dropped by the background thread. Instead,
+/// when it becomes garbage it's put on its owning thread's drop list, which is drained by this
+/// method (or when the thread exits). Calling it from inside a destructor does nothing.
+///
+/// # Example
+/// ```
+/// use shredder::{collect, drain_thread_local_drops, Gc};
+///
+/// drop(Gc::new_thread_affine(128));
+/// collect();
+/// // Now the data can be dropped on this thread
+/// drain_thread_local_drops();
+/// ```
+pub fn drain_thread_local_drops() -> usize {
+    COLLECTOR.drain_thread_local_drops()
+}
+
+/// Run `f` with a scoped heap, which destroys everything allocated in it before this returns.
+///
+/// Data allocated with `Scope::alloc` doesn't need to be `'static`, since it can never be dropped
+/// after `scope` returns. It gets an ordinary `drop` (with no need for `Finalize`), which always
+/// runs on the current thread. When the scope ends this blocks until no other thread is accessing
+/// scope data, then runs all the destructors that haven't run yet. If a `Gc` allocated in the
+/// scope survives past the end of the scope, accessing it will panic.
+///
+/// # Example
+/// ```
+/// use std::cell::Cell;
+/// use shredder::{scope, Scan, R};
+///
+/// #[derive(Scan)]
+/// struct Borrower<'a> {
+///     log: R<'a, Cell<u32>>,
+/// }
+///
+/// impl Drop for Borrower<'_> {
+///     fn drop(&mut self) {
+///         // This is fine, since the scope ends before `log` does (and this runs on the scope's
+///         // thread, even if the data is collected early)
+///         self.log.set(self.log.get() + 1);
+///     }
+/// }
+///
+/// let log = Cell::new(0);
+/// scope(|s| {
+///     let data = s.alloc(Borrower { log: R::new(&log) });
+///     let _also_data = data.clone();
+/// });
+/// assert_eq!(log.get(), 1);
+/// ```
+pub fn scope<'env, R, F: FnOnce(&Scope<'env>) -> R>(f: F) -> R {
+    let scope = Scope::new();
+    f(&scope)
+}
+
 /// A convenience method for helping ensure your destructors are run.
 ///
 /// In Rust you can never assume that destructors run, but using this method helps `shredder` not
@@ -192,6 +527,7 @@ pub fn run_with_gc_cleanup<T, F: FnOnce() -> T>(f: F) -> T {
 
     collect();
     synchronize_destructors();
+    drain_thread_local_drops();
 
     res
 }
@@ -0,0 +1,326 @@
+use std::alloc::{dealloc, Layout};
+use std::cell::{Cell, RefCell};
+use std::cmp;
+use std::fmt::{self, Debug, Formatter};
+use std::mem;
+use std::ops::Deref;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{GcSafe, Scan, Scanner};
+
+const LOCAL_MIN_ALLOCATIONS_FOR_COLLECTION: usize = 1024;
+const LOCAL_ALLOCATION_TRIGGER_PERCENT: usize = 75;
+
+/// What the local heap sees when scanning data: a pointer to some other piece of local data
+pub(crate) type LocalGcRef = NonNull<LocalGcBox<dyn Scan>>;
+
+/// A garbage collected smart pointer for data that never leaves the current thread.
+///
+/// `LocalGc` is a single-threaded alternative to `Gc`. Each thread has its own local heap, which is
+/// collected synchronously on that thread, so there are no atomics, locks, or background threads
+/// involved. That means access is just a `Deref`, with no guards needed. It uses the same `Scan`
+/// trait (and derive) as `Gc`.
+///
+/// Local data is collected when you call `collect_local`, or automatically when allocating. Its
+/// destructors run on the owning thread, during collection.
+///
+/// A `LocalGc` can be stored inside a `Gc` (it implements `Scan`), but this leaks: a `Gc`'s data is
+/// usually dropped by a background thread, which can't update the owning thread's heap. So the
+/// local data is never collected (and its destructor never runs). Avoid putting `LocalGc`s in
+/// `Gc`s unless the `Gc` is thread-affine (see `Gc::new_thread_affine`).
+///
+/// # Example
+/// ```
+/// use std::cell::RefCell;
+/// use shredder::{collect_local, number_of_local_allocations, LocalGc, Scan};
+///
+/// #[derive(Scan)]
+/// struct Node {
+///     edges: RefCell<Vec<LocalGc<Node>>>,
+/// }
+///
+/// let a = LocalGc::new(Node { edges: RefCell::new(Vec::new()) });
+/// let b = LocalGc::new(Node { edges: RefCell::new(vec![a.clone()]) });
+/// a.edges.borrow_mut().push(b);
+/// drop(a);
+///
+/// collect_local();
+/// assert_eq!(number_of_local_allocations(), 0);
+/// ```
+pub struct LocalGc<T: Scan + 'static> {
+    ptr: NonNull<LocalGcBox<T>>,
+}
+
+pub(crate) struct LocalGcBox<T: ?Sized> {
+    header: LocalGcHeader,
+    value: T,
+}
+
+struct LocalGcHeader {
+    /// the thread whose local heap owns this data
+    owner: u64,
+    /// how many `LocalGc`s point at this data
+    handles: Cell<usize>,
+    /// during collection, how many of those `LocalGc`s live inside other local data
+    internal_handles: Cell<usize>,
+    /// during collection, have we found this data to be reachable?
+    marked: Cell<bool>,
+    /// has this data been collected? (it may still be accessed from a destructor)
+    dead: Cell<bool>,
+    /// set once collected data has been dropped, but a `LocalGc` still points at it. The memory is
+    /// then freed by the last `LocalGc` to be dropped
+    awaiting_last_handle: Cell<bool>,
+}
+
+struct LocalHeap {
+    objects: RefCell<Vec<LocalGcRef>>,
+    collect_threshold: Cell<usize>,
+    collecting: Cell<bool>,
+}
+
+static NEXT_THREAD_TOKEN: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    static LOCAL_HEAP: LocalHeap = LocalHeap::new();
+
+    // A unique id for this thread. This can be read even while the thread is exiting
+    static THREAD_TOKEN: Cell<u64> = const { Cell::new(0) };
+}
+
+fn current_thread_token() -> u64 {
+    THREAD_TOKEN.with(|token| {
+        if token.get() == 0 {
+            token.set(NEXT_THREAD_TOKEN.fetch_add(1, Ordering::Relaxed));
+        }
+        token.get()
+    })
+}
+
+impl<T: Scan + 'static> LocalGc<T> {
+    /// Create a new `LocalGc` containing the given data, tracked by this thread's local heap.
+    ///
+    /// When this data is garbage collected, its `drop` implementation will be run on this thread.
+    pub fn new(v: T) -> Self {
+        let data = Box::new(LocalGcBox {
+            header: LocalGcHeader {
+                owner: current_thread_token(),
+                handles: Cell::new(1),
+                internal_handles: Cell::new(0),
+                marked: Cell::new(false),
+                dead: Cell::new(false),
+                awaiting_last_handle: Cell::new(false),
+            },
+            value: v,
+        });
+        let ptr = unsafe { NonNull::new_unchecked(Box::into_raw(data)) };
+
+        // If the heap is already gone (we're in a thread-local destructor), this data just leaks
+        let _ = LOCAL_HEAP.try_with(|heap| heap.track(ptr));
+
+        Self { ptr }
+    }
+
+    fn header(&self) -> &LocalGcHeader {
+        // The heap never frees data that has a handle pointing to it (see `awaiting_last_handle`)
+        unsafe { &(*self.ptr.as_ptr()).header }
+    }
+}
+
+impl<T: Scan + 'static> Deref for LocalGc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        assert!(
+            !self.header().dead.get(),
+            "Tried to access a LocalGc after it was collected (perhaps you're manipulating LocalGc<?> in a destructor?)"
+        );
+
+        unsafe { &(*self.ptr.as_ptr()).value }
+    }
+}
+
+impl<T: Scan + 'static> Clone for LocalGc<T> {
+    fn clone(&self) -> Self {
+        let header = self.header();
+        header.handles.set(header.handles.get() + 1);
+
+        Self { ptr: self.ptr }
+    }
+}
+
+impl<T: Scan + 'static> Drop for LocalGc<T> {
+    fn drop(&mut self) {
+        let header = self.header();
+
+        // A `LocalGc` inside a `Gc` may be dropped by the background thread. We can't touch the
+        // owner's heap from here, so we leak the data instead
+        if header.owner != current_thread_token() {
+            return;
+        }
+
+        let handles = header.handles.get() - 1;
+        header.handles.set(handles);
+
+        // A destructor kept this handle to collected data, so the heap left the memory to us
+        if handles == 0 && header.awaiting_last_handle.get() {
+            let layout = Layout::new::<LocalGcBox<T>>();
+            unsafe { dealloc(self.ptr.as_ptr().cast::<u8>(), layout) };
+        }
+    }
+}
+
+impl<T: Scan + 'static + Debug> Debug for LocalGc<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalGc").field("v", self.deref()).finish()
+    }
+}
+
+// Safety: When the global collector scans a `LocalGc` it does nothing, and dropping a `LocalGc` on
+// the wrong thread only leaks. (So scanning and dropping are fine from any thread.)
+// These impls are what let `LocalGc`s live inside other local data, but they also let a `LocalGc`
+// be put in a `Gc`. See the leak documented on `LocalGc`.
+unsafe impl<T: Scan + 'static> GcSafe for LocalGc<T> {}
+unsafe impl<T: Scan + 'static> Scan for LocalGc<T> {
+    #[inline]
+    fn scan(&self, scanner: &mut Scanner<'_>) {
+        let local_ref: LocalGcRef = self.ptr;
+        scanner.add_local_handle(local_ref);
+    }
+}
+
+impl LocalHeap {
+    fn new() -> Self {
+        Self {
+            objects: RefCell::new(Vec::new()),
+            collect_threshold: Cell::new(LOCAL_MIN_ALLOCATIONS_FOR_COLLECTION),
+            collecting: Cell::new(false),
+        }
+    }
+
+    fn track(&self, ptr: LocalGcRef) {
+        let object_count = {
+            let mut objects = self.objects.borrow_mut();
+            objects.push(ptr);
+            objects.len()
+        };
+
+        if object_count >= self.collect_threshold.get() {
+            self.collect();
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.objects.borrow().len()
+    }
+
+    // Destructors run during this method can allocate, so we must not hold a borrow of `objects`
+    fn collect(&self) {
+        // Destructors may allocate, but they shouldn't start another collection
+        if self.collecting.replace(true) {
+            return;
+        }
+
+        let objects = mem::take(&mut *self.objects.borrow_mut());
+
+        // We do the same trick as the global collector: any data with more handles than we find
+        // inside other local data must be a root
+        for ptr in &objects {
+            let header = unsafe { &ptr.as_ref().header };
+            header.internal_handles.set(0);
+            header.marked.set(false);
+        }
+        for ptr in &objects {
+            scan_local(*ptr, |child| {
+                let child_header = unsafe { &child.as_ref().header };
+                child_header
+                    .internal_handles
+                    .set(child_header.internal_handles.get() + 1);
+            });
+        }
+
+        let mut stack: Vec<LocalGcRef> = objects
+            .iter()
+            .copied()
+            .filter(|ptr| {
+                let header = unsafe { &ptr.as_ref().header };
+                header.handles.get() > header.internal_handles.get()
+            })
+            .collect();
+        while let Some(ptr) = stack.pop() {
+            let header = unsafe { &ptr.as_ref().header };
+            if !header.marked.replace(true) {
+                scan_local(ptr, |child| stack.push(child));
+            }
+        }
+
+        let (live, dead): (Vec<_>, Vec<_>) = objects
+            .into_iter()
+            .partition(|ptr| unsafe { ptr.as_ref().header.marked.get() });
+
+        // First mark everything as dead, so destructors can't access data that's been dropped
+        let dead: Vec<(LocalGcRef, Layout)> = dead
+            .into_iter()
+            .map(|ptr| unsafe {
+                ptr.as_ref().header.dead.set(true);
+                (ptr, Layout::for_value(ptr.as_ref()))
+            })
+            .collect();
+        // Then run the destructors. (The handles they drop still point to valid memory)
+        for (ptr, _) in &dead {
+            let res = catch_unwind(AssertUnwindSafe(|| unsafe {
+                ptr::drop_in_place(ptr::addr_of_mut!((*ptr.as_ptr()).value));
+            }));
+            if let Err(e) = res {
+                eprintln!("LocalGc drop failed: {e:?}");
+            }
+        }
+        // Finally, free the memory. But a destructor may have kept a handle to dead data (say, in a
+        // thread local), and that handle still reads the header. So that data is freed once its
+        // last handle is dropped instead
+        for (ptr, layout) in dead {
+            let header = unsafe { &ptr.as_ref().header };
+            if header.handles.get() == 0 {
+                unsafe { dealloc(ptr.as_ptr().cast::<u8>(), layout) };
+            } else {
+                header.awaiting_last_handle.set(true);
+            }
+        }
+
+        let live_count = {
+            let mut objects = self.objects.borrow_mut();
+            // Keep anything allocated by a destructor
+            objects.extend(live);
+            objects.len()
+        };
+        self.collect_threshold.set(cmp::max(
+            LOCAL_MIN_ALLOCATIONS_FOR_COLLECTION,
+            live_count + live_count * LOCAL_ALLOCATION_TRIGGER_PERCENT / 100,
+        ));
+
+        self.collecting.set(false);
+    }
+}
+
+impl Drop for LocalHeap {
+    fn drop(&mut self) {
+        // The thread is exiting, so this is our last chance to run destructors
+        // Anything still reachable afterwards is leaked (since `LocalGc`s may still point at it)
+        self.collect();
+    }
+}
+
+fn scan_local<F: FnMut(LocalGcRef)>(ptr: LocalGcRef, callback: F) {
+    let mut scanner = Scanner::new_local(callback);
+    let value: &dyn Scan = unsafe { &(*ptr.as_ptr()).value };
+    value.scan(&mut scanner);
+}
+
+pub(crate) fn collect_local() {
+    LOCAL_HEAP.with(LocalHeap::collect);
+}
+
+pub(crate) fn local_allocation_count() -> usize {
+    LOCAL_HEAP.with(LocalHeap::len)
+}
@@ -1,5 +1,8 @@
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::mem;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+use std::time::Instant;
 
 use parking_lot::Condvar;
 use parking_lot::Mutex;
@@ -11,19 +14,29 @@ const EXCLUSIVE_SIGNPOST: u64 = !0;
 #[derive(Debug)]
 pub struct Lockout {
     count: AtomicU64,
-    lockout_mutex: Mutex<()>,
+    /// also holds the wakers of futures waiting for the exclusive warrant to be released
+    lockout_mutex: Mutex<Vec<Waker>>,
     lockout_condvar: Condvar,
+    /// how many threads are blocked waiting for an exclusive warrant (so releasing the last
+    /// warrant knows to wake them)
+    exclusive_waiters: AtomicUsize,
 }
 
 impl Lockout {
     pub fn new() -> Self {
         Self {
             count: AtomicU64::new(0),
-            lockout_mutex: Mutex::new(()),
+            lockout_mutex: Mutex::new(Vec::new()),
             lockout_condvar: Condvar::new(),
+            exclusive_waiters: AtomicUsize::new(0),
         }
     }
 
+    /// How much heap memory this lockout owns (the buffer for waiting futures' wakers)
+    pub fn heap_bytes(&self) -> usize {
+        self.lockout_mutex.lock().capacity() * mem::size_of::<Waker>()
+    }
+
     pub fn get_warrant<P: LockoutProvider>(provider: P) -> Warrant<P> {
         let lockout = provider.provide();
 
@@ -62,6 +75,128 @@ impl Lockout {
         }
     }
 
+    /// Like `get_warrant`, but fails instead of blocking
+    pub fn try_get_warrant<P: LockoutProvider>(provider: P) -> Option<Warrant<P>> {
+        if provider.provide().try_increment() {
+            Some(Warrant { provider })
+        } else {
+            None
+        }
+    }
+
+    /// Like `get_warrant`, but gives up at `deadline`
+    pub fn get_warrant_until<P: LockoutProvider>(
+        provider: P,
+        deadline: Instant,
+    ) -> Option<Warrant<P>> {
+        let lockout = provider.provide();
+
+        if lockout.try_increment() {
+            return Some(Warrant { provider });
+        }
+
+        let mut guard = lockout.lockout_mutex.lock();
+        loop {
+            if lockout.try_increment() {
+                drop(guard);
+                return Some(Warrant { provider });
+            }
+
+            if lockout
+                .lockout_condvar
+                .wait_until(&mut guard, deadline)
+                .timed_out()
+            {
+                // One last try, in case we were released right at the deadline
+                drop(guard);
+                return Self::try_get_warrant(provider);
+            }
+        }
+    }
+
+    /// Like `get_warrant`, but registers a waker instead of blocking
+    pub fn poll_warrant<P: LockoutProvider + Clone>(
+        provider: &P,
+        cx: &mut Context<'_>,
+    ) -> Poll<Warrant<P>> {
+        let lockout = provider.provide();
+
+        if lockout.try_increment() {
+            return Poll::Ready(Warrant {
+                provider: provider.clone(),
+            });
+        }
+
+        // The exclusive warrant takes this lock before it's released, so we can't miss the wakeup
+        let mut waiting = lockout.lockout_mutex.lock();
+        if lockout.try_increment() {
+            return Poll::Ready(Warrant {
+                provider: provider.clone(),
+            });
+        }
+
+        if !waiting.iter().any(|w| w.will_wake(cx.waker())) {
+            waiting.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+
+    // Adds one to the count, unless the count is SIGNPOSTED
+    fn try_increment(&self) -> bool {
+        let mut value = self.count.load(Ordering::SeqCst);
+        while value != EXCLUSIVE_SIGNPOST {
+            match self.count.compare_exchange_weak(
+                value,
+                value + 1,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return true,
+                Err(actual) => value = actual,
+            }
+        }
+
+        false
+    }
+
+    /// Like `get_exclusive_warrant`, but blocks until every other warrant is released (giving up at
+    /// `deadline`, if there is one)
+    pub fn wait_for_exclusive_warrant<P: LockoutProvider + Clone>(
+        provider: &P,
+        deadline: Option<Instant>,
+    ) -> Option<ExclusiveWarrant<P>> {
+        if let Some(warrant) = Self::get_exclusive_warrant(provider.clone()) {
+            return Some(warrant);
+        }
+
+        let lockout = provider.provide();
+        lockout.exclusive_waiters.fetch_add(1, Ordering::SeqCst);
+        let mut guard = lockout.lockout_mutex.lock();
+        let res = loop {
+            // Dropping the last warrant takes the lock before notifying, so we can't miss it
+            if let Some(warrant) = Self::get_exclusive_warrant(provider.clone()) {
+                break Some(warrant);
+            }
+
+            match deadline {
+                Some(deadline) => {
+                    if lockout
+                        .lockout_condvar
+                        .wait_until(&mut guard, deadline)
+                        .timed_out()
+                    {
+                        break Self::get_exclusive_warrant(provider.clone());
+                    }
+                }
+                None => lockout.lockout_condvar.wait(&mut guard),
+            }
+        };
+        drop(guard);
+        lockout.exclusive_waiters.fetch_sub(1, Ordering::SeqCst);
+
+        res
+    }
+
     pub fn get_exclusive_warrant<P: LockoutProvider>(provider: P) -> Option<ExclusiveWarrant<P>> {
         let lockout = provider.provide();
 
@@ -93,6 +228,11 @@ impl<P: LockoutProvider> Drop for Warrant<P> {
                 .count
                 .compare_and_swap(count, count - 1, Ordering::SeqCst);
             if prev_value == count {
+                // If this was the last warrant, someone may be waiting for an exclusive one
+                if count == 1 && lockout.exclusive_waiters.load(Ordering::SeqCst) != 0 {
+                    let _guard = lockout.lockout_mutex.lock();
+                    lockout.lockout_condvar.notify_all();
+                }
                 return;
             }
         }
@@ -108,12 +248,20 @@ impl<P: LockoutProvider> Drop for ExclusiveWarrant<P> {
     fn drop(&mut self) {
         let lockout = self.provider.provide();
 
-        let _guard = lockout.lockout_mutex.lock();
-        let prev_count = lockout
-            .count
-            .compare_and_swap(EXCLUSIVE_SIGNPOST, 0, Ordering::SeqCst);
-        assert_eq!(prev_count, EXCLUSIVE_SIGNPOST);
-        lockout.lockout_condvar.notify_all();
+        let waiting = {
+            let mut waiting = lockout.lockout_mutex.lock();
+            let prev_count =
+                lockout
+                    .count
+                    .compare_and_swap(EXCLUSIVE_SIGNPOST, 0, Ordering::SeqCst);
+            assert_eq!(prev_count, EXCLUSIVE_SIGNPOST);
+            lockout.lockout_condvar.notify_all();
+            mem::take(&mut *waiting)
+        };
+
+        for waker in waiting {
+            waker.wake();
+        }
     }
 }
 
@@ -130,7 +278,11 @@ impl LockoutProvider for Arc<Lockout> {
 // TODO(issue): https://github.com/Others/shredder/issues/10
 #[cfg(test)]
 mod test {
+    use std::sync::atomic::{AtomicBool, Ordering};
     use std::sync::Arc;
+    use std::task::{Context, Wake, Waker};
+    use std::thread;
+    use std::time::{Duration, Instant};
 
     use super::Lockout;
 
@@ -155,4 +307,42 @@ mod test {
         let _warrant_1 = Lockout::get_warrant(lockout.clone());
         let _warrant_2 = Lockout::get_warrant(lockout);
     }
+
+    #[test]
+    fn exclusive_warrant_wakes_polled_warrant() {
+        struct Flag(AtomicBool);
+        impl Wake for Flag {
+            fn wake(self: Arc<Self>) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let flag = Arc::new(Flag(AtomicBool::new(false)));
+        let waker = Waker::from(flag.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let lockout = Arc::new(Lockout::new());
+        let exclusive_warrant = Lockout::get_exclusive_warrant(lockout.clone());
+        assert!(Lockout::poll_warrant(&lockout, &mut cx).is_pending());
+
+        drop(exclusive_warrant);
+        assert!(flag.0.load(Ordering::SeqCst));
+        assert!(Lockout::poll_warrant(&lockout, &mut cx).is_ready());
+    }
+
+    #[test]
+    fn releasing_warrants_wakes_exclusive_waiter() {
+        let lockout = Arc::new(Lockout::new());
+        let warrant = Lockout::get_warrant(lockout.clone());
+
+        let deadline = Instant::now() + Duration::from_millis(10);
+        assert!(Lockout::wait_for_exclusive_warrant(&lockout, Some(deadline)).is_none());
+
+        let releaser = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            drop(warrant);
+        });
+        assert!(Lockout::wait_for_exclusive_warrant(&lockout, None).is_some());
+        releaser.join().unwrap();
+    }
 }
@@ -1,3 +1,4 @@
+mod parking_lot_impls;
 mod r;
 mod std_impls;
 
@@ -5,6 +6,7 @@ use std::hash::{Hash, Hasher};
 use std::ops::{Deref, DerefMut};
 
 use crate::collector::InternalGcRef;
+use crate::local::LocalGcRef;
 use crate::Gc;
 
 pub use r::{RMut, R};
@@ -96,8 +98,11 @@ pub unsafe trait Scan: GcSafe {
 /// Data that is `GcSafe` satisfies the following requirements:
 /// 1) It's okay for any thread to call `scan`, as long as it has exclusive access to the data
 /// 2) If this data is `'static`, any thread can drop the data safely
+///
 /// Requirement (1) can be relaxed if you can ensure that the type does not implement `Scan`
 /// (A negative impl can be used to ensure this constraint.)
+/// Requirement (2) can be relaxed if the data is only ever put in a `Gc` with
+/// `Gc::new_thread_affine`, since then it is only dropped on the thread that created it.
 ///
 /// Importantly if a type is Send, then it is always `GcSafe`
 ///
@@ -108,6 +113,8 @@ pub unsafe trait GcSafe {}
 /// Usually you will only care about this while implementing `Scan`
 pub struct Scanner<'a> {
     scan_callback: Box<dyn FnMut(InternalGcRef) + 'a>,
+    /// only present when scanning for a local heap (which doesn't care about `Gc`s)
+    local_scan_callback: Option<Box<dyn FnMut(LocalGcRef) + 'a>>,
 }
 
 #[allow(clippy::unused_self)]
@@ -116,34 +123,49 @@ impl<'a> Scanner<'a> {
     pub(crate) fn new<F: FnMut(InternalGcRef) + 'a>(callback: F) -> Self {
         Self {
             scan_callback: Box::new(callback),
+            local_scan_callback: None,
+        }
+    }
+
+    #[must_use]
+    pub(crate) fn new_local<F: FnMut(LocalGcRef) + 'a>(callback: F) -> Self {
+        Self {
+            scan_callback: Box::new(|_| {}),
+            local_scan_callback: Some(Box::new(callback)),
         }
     }
 
     /// Scan a piece of data, tracking any `Gc`s found
-    pub fn scan<T: Scan>(&mut self, from: &T) {
+    pub fn scan<T: Scan + ?Sized>(&mut self, from: &T) {
         from.scan(self);
     }
 
     /// This function is used internally to fail the `Scan` derive if a field is not `GcSafe`
     /// It's a little bit of a kludge, but that's okay for now
     #[doc(hidden)]
-    pub fn check_gc_safe<T: GcSafe>(&self, _: &T) {}
+    pub fn check_gc_safe<T: GcSafe + ?Sized>(&self, _: &T) {}
 
-    fn add_internal_handle<T: Scan>(&mut self, gc: &Gc<T>) {
+    fn add_internal_handle<T: Scan + ?Sized>(&mut self, gc: &Gc<T>) {
         (self.scan_callback)(gc.internal_handle());
     }
+
+    pub(crate) fn add_local_handle(&mut self, local_ref: LocalGcRef) {
+        if let Some(local_scan_callback) = &mut self.local_scan_callback {
+            local_scan_callback(local_ref);
+        }
+    }
 }
 
 // This is a fundamental implementation, since it's how GcInternalHandles make it into the Scanner
 // Safety: The implementation is built around this, so it's by definition safe
-unsafe impl<T: Scan> Scan for Gc<T> {
+unsafe impl<T: Scan + ?Sized> Scan for Gc<T> {
     #[allow(clippy::inline_always)]
     #[inline(always)]
     fn scan(&self, scanner: &mut Scanner<'_>) {
         scanner.add_internal_handle(self)
     }
 }
-unsafe impl<T: Scan> GcSafe for Gc<T> {}
+unsafe impl<T: Scan + ?Sized> GcSafe for Gc<T> {}
 
 // FIXME: This macro can be removed once we have overlapping marker traits
 //        (https://github.com/rust-lang/rust/issues/29864)
@@ -0,0 +1,33 @@
+use parking_lot::{Mutex, RwLock};
+
+use crate::{GcSafe, Scan, Scanner};
+
+// Skipping the contents of a held lock is fine: the only way to lock one inside a `Gc` is through
+// a guard that also holds a warrant on the data. So the collector never scans data that's locked,
+// it treats the data as in use instead (and everything it points to as reachable).
+unsafe impl<T: Scan> Scan for Mutex<T> {
+    #[inline]
+    fn scan(&self, scanner: &mut Scanner<'_>) {
+        if let Some(data) = self.try_lock() {
+            let raw: &T = &data;
+            scanner.scan(raw);
+        } else {
+            error!("A parking_lot::Mutex was in use when it was scanned -- something is buggy here! (no memory unsafety yet, so proceeding...)");
+        }
+    }
+}
+unsafe impl<T: GcSafe> GcSafe for Mutex<T> {}
+
+unsafe impl<T: Scan> Scan for RwLock<T> {
+    #[inline]
+    fn scan(&self, scanner: &mut Scanner<'_>) {
+        // (An upgradable read doesn't block this, but it only ever upgrades while holding a warrant)
+        if let Some(data) = self.try_read() {
+            let raw: &T = &data;
+            scanner.scan(raw);
+        } else {
+            error!("A parking_lot::RwLock was in use when it was scanned -- something is buggy here! (no memory unsafety yet, so proceeding...)");
+        }
+    }
+}
+unsafe impl<T: GcSafe> GcSafe for RwLock<T> {}
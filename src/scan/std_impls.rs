@@ -29,6 +29,16 @@ unsafe impl<T: Scan> Scan for Vec<T> {
 }
 unsafe impl<T: GcSafe> GcSafe for Vec<T> {}
 
+unsafe impl<T: Scan> Scan for [T] {
+    #[inline]
+    fn scan(&self, scanner: &mut Scanner<'_>) {
+        for e in self {
+            scanner.scan(e);
+        }
+    }
+}
+unsafe impl<T: GcSafe> GcSafe for [T] {}
+
 unsafe impl<T: Scan, S: BuildHasher> Scan for HashSet<T, S> {
     #[inline]
     fn scan(&self, scanner: &mut Scanner<'_>) {
@@ -66,6 +76,14 @@ unsafe impl<T: Scan> Scan for RefCell<T> {
 }
 unsafe impl<T: GcSafe> GcSafe for RefCell<T> {}
 
+unsafe impl<T: Scan + ?Sized> Scan for Box<T> {
+    #[inline]
+    fn scan(&self, scanner: &mut Scanner<'_>) {
+        scanner.scan(&**self);
+    }
+}
+unsafe impl<T: GcSafe + ?Sized> GcSafe for Box<T> {}
+
 unsafe impl<T: Scan> Scan for Option<T> {
     #[inline]
     fn scan(&self, scanner: &mut Scanner<'_>) {
@@ -87,8 +105,11 @@ unsafe impl<T: Scan> Scan for Mutex<T> {
             Err(TryLockError::WouldBlock) => {
                 error!("A Mutex was in use when it was scanned -- something is buggy here! (no memory unsafety yet, so proceeding...)");
             }
-            Err(TryLockError::Poisoned(_)) => {
-                // TODO(issue): https://github.com/Others/shredder/issues/6
+            Err(TryLockError::Poisoned(e)) => {
+                // Poisoned data is still reachable, so the `Gc`s inside it must be scanned
+                let data = e.into_inner();
+                let raw: &T = data.deref();
+                scanner.scan(raw);
             }
         }
     }
@@ -106,8 +127,11 @@ unsafe impl<T: Scan> Scan for RwLock<T> {
             Err(TryLockError::WouldBlock) => {
                 error!("A RwLock was in use when it was scanned -- something is buggy here! (no memory unsafety yet, so proceeding...)");
             }
-            Err(TryLockError::Poisoned(_)) => {
-                // TODO(issue): https://github.com/Others/shredder/issues/6
+            Err(TryLockError::Poisoned(e)) => {
+                // Poisoned data is still reachable, so the `Gc`s inside it must be scanned
+                let data = e.into_inner();
+                let raw: &T = data.deref();
+                scanner.scan(raw);
             }
         }
     }
@@ -136,6 +160,7 @@ impl_empty_scan_for_send_type!(u128);
 // It's nice if other send types from std also get the scan treatment
 // These are value types that have no internal content needing a scan
 impl_empty_scan_for_send_type!(String);
+impl_empty_scan_for_send_type!(str);
 
 impl_empty_scan_for_send_type!(Duration);
 impl_empty_scan_for_send_type!(Instant);
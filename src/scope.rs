@@ -0,0 +1,69 @@
+use std::marker::PhantomData;
+use std::mem;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use crate::collector::{GcData, COLLECTOR};
+use crate::{Gc, Scan};
+
+/// A scoped heap, created with `shredder::scope`.
+///
+/// Everything allocated with `alloc` is destroyed before `scope` returns, so data allocated here
+/// only needs to live as long as `'env` (rather than being `'static`). That means it can have an
+/// ordinary `Drop` implementation, and there's no need for `R`, `RMut`, or `Finalize`.
+///
+/// If a `Gc` allocated in a scope outlives the scope, trying to access its data will panic. (Or
+/// return `GcError::Deallocated`, if you use `Gc::checked_get`.) If a guard on scoped data is
+/// still held when the scope ends, the scope waits for it to be released. So don't return a guard
+/// from the scope: that deadlocks.
+///
+/// `Scope` can't be shared with other threads, since data allocated here is always dropped on the
+/// scope's thread, even if it isn't `Send`:
+/// ```compile_fail
+/// shredder::scope(|s| {
+///     std::thread::scope(|t| {
+///         t.spawn(|| s.alloc(1_u32));
+///     });
+/// });
+/// ```
+pub struct Scope<'env> {
+    allocations: Mutex<Vec<Arc<GcData>>>,
+    // Invariant over `'env`, so the compiler can't shrink it to fit shorter lived data
+    _env: PhantomData<&'env mut &'env ()>,
+    // Neither `Send` nor `Sync`, so `alloc` is only ever called on the scope's thread
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<'env> Scope<'env> {
+    pub(crate) fn new() -> Self {
+        Self {
+            allocations: Mutex::new(Vec::new()),
+            _env: PhantomData,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Create a new `Gc` containing the given data, tracked by this scope.
+    ///
+    /// When this data is garbage collected (or when the scope ends, whichever comes first) its
+    /// `drop` implementation will be run. `T` doesn't need to be `Send`, since its destructor always
+    /// runs on this thread: data collected during the scope waits for this thread to call
+    /// `drain_thread_local_drops`, or for the scope to end.
+    pub fn alloc<T: Scan + 'env>(&self, v: T) -> Gc<T> {
+        // Safety: We call `destroy_now` on this data when the scope is dropped, which always
+        // happens before `'env` ends
+        let gc = unsafe { Gc::new_with_unchecked_drop(v) };
+        self.allocations.lock().push(gc.internal_handle().data());
+
+        gc
+    }
+}
+
+impl Drop for Scope<'_> {
+    fn drop(&mut self) {
+        // This runs even if the scope is unwinding, which is essential for safety
+        let allocations = mem::take(&mut *self.allocations.lock());
+        COLLECTOR.destroy_now(&allocations);
+    }
+}
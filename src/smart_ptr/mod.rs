@@ -1,22 +1,34 @@
+use std::any::TypeId;
 use std::borrow::Borrow;
 use std::cell::{BorrowError, BorrowMutError, RefCell};
 use std::cmp::Ordering;
 use std::fmt::{self, Debug, Display, Formatter};
 use std::hash::{Hash, Hasher};
-use std::ops::Deref;
+use std::iter::FromIterator;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::ptr;
 use std::sync;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 
 use stable_deref_trait::StableDeref;
 
-use crate::collector::{GcGuardWarrant, InternalGcRef, COLLECTOR};
+use crate::collector::{GcGuardExclusiveWarrant, GcGuardWarrant, InternalGcRef, COLLECTOR};
+use crate::wrappers::{lock_until, GcLockError, GcLockTimeoutError};
 use crate::wrappers::{
-    GcMutexGuard, GcPoisonError, GcRef, GcRefMut, GcRwLockReadGuard, GcRwLockWriteGuard,
-    GcTryLockError,
+    GcAsyncMutexLockFuture, GcGetFuture, GcMappedGuard, GcMappedGuardMut, GcParkingLotMutexGuard,
+    GcParkingLotRwLockReadGuard, GcParkingLotRwLockUpgradableReadGuard,
+    GcParkingLotRwLockWriteGuard, GcRef, GcRefMut, OwnedGcRef, OwnedGcRefMut,
 };
-use crate::{Finalize, Scan};
+use crate::wrappers::{
+    GcMutexGuard, GcPoisonError, GcRwLockReadGuard, GcRwLockWriteGuard, GcTryLockError,
+    OwnedGcMutexGuard, OwnedGcRwLockReadGuard, OwnedGcRwLockWriteGuard,
+};
+use crate::{Finalize, GcAny, GcAsyncMutex, GcError, Scan};
 
 /// A smart-pointer for data tracked by `shredder` garbage collector
-pub struct Gc<T: Scan> {
+pub struct Gc<T: Scan + ?Sized> {
     backing_handle: InternalGcRef,
     direct_ptr: *const T,
 }
@@ -42,6 +54,97 @@ impl<T: Scan> Gc<T> {
         }
     }
 
+    /// Like `new`, but fails instead of taking the current `AllocationContext` past its quota
+    /// (see `set_context_quota`). Outside of a context, this always succeeds.
+    ///
+    /// # Errors
+    /// Returns `GcError::QuotaExceeded` (dropping `v`) if the allocation would exceed the quota
+    ///
+    /// # Example
+    /// ```
+    /// use shredder::Gc;
+    ///
+    /// let data = Gc::try_new(5).unwrap();
+    /// assert_eq!(*data.get(), 5);
+    /// ```
+    pub fn try_new(v: T) -> Result<Self, GcError>
+    where
+        T: 'static,
+    {
+        let (handle, ptr) = COLLECTOR.try_track_with_drop(v)?;
+        Ok(Self {
+            backing_handle: handle,
+            direct_ptr: ptr,
+        })
+    }
+
+    /// Create a new `Gc` whose data can contain `Gc`s pointing back at itself.
+    /// `T: 'static` in order to create a `Gc<T>` with this method.
+    ///
+    /// `data_fn` is given a `Gc` pointing at the (not yet constructed) data, which it can clone
+    /// and store in the value it returns. Calling `get` on that `Gc` (or its clones) before
+    /// `new_cyclic` returns will panic. If `data_fn` panics, those `Gc`s will panic on access.
+    ///
+    /// When this data is garbage collected, its `drop` implementation will be run.
+    ///
+    /// # Example
+    /// ```
+    /// use shredder::{Gc, Scan};
+    ///
+    /// #[derive(Scan)]
+    /// struct Node {
+    ///     me: Gc<Node>,
+    ///     value: u32,
+    /// }
+    ///
+    /// let node = Gc::new_cyclic(|me| Node {
+    ///     me: me.clone(),
+    ///     value: 7,
+    /// });
+    /// assert_eq!(node.get().me.get().value, 7);
+    /// ```
+    pub fn new_cyclic<F>(data_fn: F) -> Self
+    where
+        T: 'static,
+        F: FnOnce(&Self) -> T,
+    {
+        let (handle, ptr) = COLLECTOR.track_uninit_with_drop();
+        let this = Self {
+            backing_handle: handle,
+            direct_ptr: ptr,
+        };
+
+        // If `data_fn` panics, the allocation is never initialized
+        let abandon_on_unwind = AbandonOnUnwind(&this.backing_handle);
+        let v = data_fn(&this);
+        mem::forget(abandon_on_unwind);
+
+        // Safety: `this` came from `track_uninit_with_drop`, and we've not abandoned it
+        unsafe { COLLECTOR.initialize(&this.backing_handle, ptr, v) };
+        this
+    }
+
+    /// Create a new `Gc` containing the given data, whose destructor must run on this thread.
+    /// `T: 'static` in order to create a `Gc<T>` with this method.
+    ///
+    /// This is useful for data that can't be dropped on the background thread, like a handle to an
+    /// OpenGL context. (`T`'s `GcSafe` implementation only needs to allow scanning from any thread.)
+    /// When this data is garbage collected, it is put on this thread's drop list. Its `drop`
+    /// implementation then runs when this thread calls `drain_thread_local_drops`, or when this
+    /// thread exits.
+    ///
+    /// If the data is collected after this thread has exited, its destructor will never run.
+    pub fn new_thread_affine(v: T) -> Self
+    where
+        T: 'static,
+    {
+        let (handle, ptr) = COLLECTOR.track_thread_affine(v);
+        Self {
+            backing_handle: handle,
+            direct_ptr: ptr,
+        }
+    }
+
     /// Create a new `Gc` containing the given data. (But specifying not to run its destructor.)
     /// This is useful because `T: 'static` is no longer necessary!
     ///
@@ -75,6 +178,60 @@ impl<T: Scan> Gc<T> {
         }
     }
 
+    /// Create a new `Gc` whose destructor will be run on this thread, without requiring `T: 'static`
+    ///
+    /// Safety: `destroy_now` must be called on this data (on this thread) before `T`'s lifetime ends
+    pub(crate) unsafe fn new_with_unchecked_drop(v: T) -> Self {
+        let (handle, ptr) = COLLECTOR.track_with_unchecked_drop(v);
+        Self {
+            backing_handle: handle,
+            direct_ptr: ptr,
+        }
+    }
+}
+
+impl<T: Scan + ?Sized> Gc<T> {
+    /// Create a new `Gc` from a boxed value. The box's allocation is reused, so `T` can be unsized.
+    /// `T: 'static` in order to create a `Gc<T>` with this method.
+    ///
+    /// This is how you get a `Gc<dyn Trait>`, as long as `Trait: Scan`. (Our own `Gc` can't
+    /// support unsizing coercions directly, since that's an unstable feature.)
+    ///
+    /// When this data is garbage collected, its `drop` implementation will be run.
+    ///
+    /// # Example
+    /// ```
+    /// use shredder::{Gc, Scan};
+    ///
+    /// trait Node: Scan {
+    ///     fn value(&self) -> u32;
+    /// }
+    ///
+    /// #[derive(Scan)]
+    /// struct Leaf(u32);
+    ///
+    /// impl Node for Leaf {
+    ///     fn value(&self) -> u32 {
+    ///         self.0
+    ///     }
+    /// }
+    ///
+    /// let node: Gc<dyn Node> = Gc::from_box(Box::new(Leaf(7)));
+    /// assert_eq!(node.get().value(), 7);
+    /// ```
+    pub fn from_box(v: Box<T>) -> Self
+    where
+        T: 'static,
+    {
+        // We track the box itself, so the data never moves
+        let (handle, box_ptr) = COLLECTOR.track_box_with_drop(v);
+        let ptr: *const T = unsafe { ptr::addr_of!(**box_ptr) };
+        Self {
+            backing_handle: handle,
+            direct_ptr: ptr,
+        }
+    }
+
     /// `get` lets you get a `GcGuard`, which will deref to the underlying data.
     ///
     /// `get` is used to get a `GcGuard`. This is usually what you want when accessing non-`Sync`
@@ -89,12 +246,305 @@ impl<T: Scan> Gc<T> {
         }
     }
 
+    /// Like `get`, but returns an error instead of panicking if the data can't be accessed. (For
+    /// instance, because this `Gc` is being used in a destructor, and its data is already gone.)
+    ///
+    /// # Example
+    /// ```
+    /// use shredder::{scope, GcError};
+    ///
+    /// // This `Gc` outlives its scope, so its data is destroyed when the scope ends
+    /// let escaped = scope(|s| s.alloc(7));
+    /// assert!(!escaped.is_live());
+    /// assert_eq!(escaped.checked_get().err(), Some(GcError::Deallocated));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns `GcError::Deallocated` if the data has been deallocated, or
+    /// `GcError::Uninitialized` if it's accessed while `Gc::new_cyclic` is constructing it
+    pub fn checked_get(&self) -> Result<GcGuard<'_, T>, GcError> {
+        let warrant = COLLECTOR.checked_get_data_warrant(&self.backing_handle)?;
+        Ok(GcGuard {
+            gc_ptr: self,
+            _warrant: warrant,
+        })
+    }
+
+    /// Returns `true` if the data in this `Gc` can be accessed. (It can't be once it's been
+    /// deallocated, or while `Gc::new_cyclic` is still constructing it.)
+    ///
+    /// Note that if this `Gc` is inside data being destroyed, the answer may change right after
+    /// it's returned. Use `checked_get` to access the data without a race.
+    #[must_use]
+    pub fn is_live(&self) -> bool {
+        COLLECTOR.is_live(&self.backing_handle)
+    }
+
+    /// Returns an id for the data in this `Gc`. Every `Gc` pointing at the same data has the same
+    /// id, and ids are never reused (even after the data is collected).
+    ///
+    /// This doesn't access the data, so unlike `Eq` or `Hash` it never blocks or recurses.
+    #[must_use]
+    pub fn id(&self) -> u64 {
+        self.backing_handle.data_id()
+    }
+
+    /// Returns the name of the type this data was allocated as, for diagnostics. (Like
+    /// `std::any::type_name`, the exact text isn't guaranteed to stay the same between compiler
+    /// versions.)
+    ///
+    /// For data allocated with `Gc::from_box`, this is the type behind the `Box`. So a `Gc<dyn Trait>`
+    /// made from a `Box<dyn Trait>` reports `dyn Trait`, while one made with `Gc::into_any` reports the
+    /// original type.
+    #[must_use]
+    pub fn type_name(&self) -> &'static str {
+        self.backing_handle.type_name()
+    }
+
+    /// The type the data was allocated as, if that says what it really is. (A boxed `dyn GcAny`
+    /// given to `from_box` only records that it's a `dyn GcAny`.)
+    fn recorded_type_id(&self) -> Option<TypeId> {
+        self.backing_handle.type_id().filter(|&type_id| {
+            type_id != TypeId::of::<dyn GcAny>()
+                && type_id != TypeId::of::<dyn GcAny + Send + Sync>()
+        })
+    }
+
+    /// Returns `true` if both `Gc`s point at the same data, like `Rc::ptr_eq`. (The `Eq` impl
+    /// compares the data itself instead.)
+    ///
+    /// # Example
+    /// ```
+    /// use shredder::Gc;
+    ///
+    /// let a = Gc::new(1);
+    /// let b = Gc::new(1);
+    /// assert!(Gc::ptr_eq(&a, &a.clone()));
+    /// assert!(!Gc::ptr_eq(&a, &b));
+    /// assert_eq!(a, b);
+    /// ```
+    #[must_use]
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.id() == other.id()
+    }
+
+    /// Consume this `Gc`, returning a raw pointer to it. (This is an associated function, so it
+    /// can't be confused with a method on `T`.)
+    ///
+    /// The `Gc` is moved into a box, so it stays registered with the collector and its data is
+    /// treated as a root until the pointer is passed back to `Gc::from_raw`. This makes it possible
+    /// to hand data to C code as `void*` user data. Each call returns a pointer of its own, so
+    /// `from_raw` always knows which `Gc` it's reclaiming.
+    ///
+    /// # Example
+    /// ```
+    /// use std::ffi::c_void;
+    ///
+    /// use shredder::Gc;
+    ///
+    /// let user_data: *mut c_void = Gc::into_raw(Gc::new(7_u32)) as *mut c_void;
+    /// // <C code holds onto `user_data`, then calls us back with it>
+    /// let gc = unsafe { Gc::from_raw(user_data as *const Gc<u32>) };
+    /// assert_eq!(*gc.get(), 7);
+    /// ```
+    #[must_use]
+    pub fn into_raw(this: Self) -> *const Self {
+        let ptr = Box::into_raw(Box::new(this)).cast_const();
+        COLLECTOR.stash_raw_handle(ptr as usize, mem::size_of::<Self>());
+        ptr
+    }
+
+    /// Reclaim a `Gc` from a pointer returned by `Gc::into_raw`
+    ///
+    /// Each call to `into_raw` can be matched by exactly one call to `from_raw`. Reclaiming the same
+    /// pointer more times than that is caught by the collector's handle table, and panics.
+    ///
+    /// # Safety
+    /// `ptr` must have come from `Gc::<T>::into_raw` (with this same `T`).
+    ///
+    /// # Panics
+    /// Panics if the `Gc` given away for this pointer has already been reclaimed
+    pub unsafe fn from_raw(ptr: *const Self) -> Self {
+        // Check before touching the box, since it's already been freed if this is a double reclaim
+        assert!(
+            COLLECTOR.reclaim_raw_handle(ptr as usize),
+            "Gc::from_raw was called on a pointer that has no `Gc` left to reclaim (was it reclaimed twice?)"
+        );
+        *Box::from_raw(ptr.cast_mut())
+    }
+
+    /// Like `checked_get`, but gives up if the collector is still scanning this data after
+    /// `timeout`. (With `Duration::ZERO` it gives up right away, instead of blocking.)
+    ///
+    /// # Errors
+    /// Returns `GcError::Timeout` if the collector was scanning this data for the whole timeout,
+    /// and otherwise the same errors as `checked_get`
+    pub fn get_timeout(&self, timeout: Duration) -> Result<GcGuard<'_, T>, GcError> {
+        self.get_until(Instant::now() + timeout)
+    }
+
+    pub(crate) fn get_until(&self, deadline: Instant) -> Result<GcGuard<'_, T>, GcError> {
+        let warrant = COLLECTOR.get_data_warrant_until(&self.backing_handle, deadline)?;
+        Ok(GcGuard {
+            gc_ptr: self,
+            _warrant: warrant,
+        })
+    }
+
+    /// Like `get`, but returns a future instead of blocking while the collector is scanning this
+    /// data. This is what you want on an async executor thread.
+    ///
+    /// # Example
+    /// ```
+    /// use shredder::Gc;
+    ///
+    /// async fn add_one(data: &Gc<u32>) -> u32 {
+    ///     *data.get_async().await + 1
+    /// }
+    /// ```
+    pub fn get_async(&self) -> GcGetFuture<'_, T> {
+        GcGetFuture { gc: self }
+    }
+
+    pub(crate) fn poll_get(&self, cx: &mut Context<'_>) -> Poll<GcGuard<'_, T>> {
+        COLLECTOR
+            .poll_data_warrant(&self.backing_handle, cx)
+            .map(|warrant| GcGuard {
+                gc_ptr: self,
+                _warrant: warrant,
+            })
+    }
+
+    /// Like `get`, but the guard holds its own clone of this `Gc`, so it doesn't borrow `self`.
+    ///
+    /// That means the guard can be stored in a struct, or returned from a function that only has a
+    /// temporary `Gc`.
+    ///
+    /// # Example
+    /// ```
+    /// use shredder::{Gc, OwnedGcGuard};
+    ///
+    /// fn first(data: &Gc<Vec<Gc<u32>>>) -> OwnedGcGuard<u32> {
+    ///     data.get()[0].get_owned()
+    /// }
+    ///
+    /// let data = Gc::new(vec![Gc::new(7)]);
+    /// assert_eq!(*first(&data), 7);
+    /// ```
+    #[must_use]
+    pub fn get_owned(&self) -> OwnedGcGuard<T> {
+        let gc = self.clone();
+        let warrant = COLLECTOR.get_data_warrant(&gc.backing_handle);
+        OwnedGcGuard {
+            _warrant: warrant,
+            gc,
+        }
+    }
+
+    /// `get_mut` gives you mutable access to the underlying data, if this is the only `Gc`
+    /// pointing at it. (Including any `Gc`s inside other garbage collected data.)
+    ///
+    /// Returns `None` if there are other `Gc`s pointing at this data.
+    ///
+    /// # Example
+    /// ```
+    /// use shredder::Gc;
+    ///
+    /// let mut data = Gc::new(1);
+    /// *data.get_mut().unwrap() += 1;
+    ///
+    /// let other = data.clone();
+    /// assert!(data.get_mut().is_none());
+    /// ```
+    pub fn get_mut(&mut self) -> Option<GcGuardMut<'_, T>> {
+        // We hold `&mut self`, so while we hold the exclusive warrant nobody can make another handle
+        let warrant = COLLECTOR.get_unique_data_warrant(&self.backing_handle)?;
+        Some(GcGuardMut {
+            gc_ptr: self,
+            _warrant: warrant,
+        })
+    }
+
     pub(crate) fn internal_handle(&self) -> InternalGcRef {
         self.backing_handle.clone()
     }
 }
 
-impl<T: Scan> Clone for Gc<T> {
+impl<T: Scan> Gc<T> {
+    /// If this is the only `Gc` pointing at its data, stop tracking the data and return it.
+    /// (`Gc`s inside other garbage collected data count too.)
+    ///
+    /// # Errors
+    /// If there are other `Gc`s pointing at this data, this `Gc` is returned unchanged.
+    ///
+    /// # Example
+    /// ```
+    /// use shredder::Gc;
+    ///
+    /// let data = Gc::new(String::from("hello"));
+    /// let other = data.clone();
+    ///
+    /// let data = Gc::try_unwrap(data).unwrap_err();
+    /// drop(other);
+    /// assert_eq!(Gc::try_unwrap(data).unwrap(), "hello");
+    /// ```
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        // Safety: `direct_ptr` is the pointer we got when this data was tracked
+        match unsafe { COLLECTOR.try_take(&this.backing_handle, this.direct_ptr) } {
+            Some(v) => Ok(v),
+            None => Err(this),
+        }
+    }
+
+    /// Like `get_mut`, but if there are other `Gc`s pointing at this data, this `Gc` is first
+    /// pointed at a new clone of the data. (So this is copy-on-write.)
+    ///
+    /// # Example
+    /// ```
+    /// use shredder::Gc;
+    ///
+    /// let mut data = Gc::new(1);
+    /// let other = data.clone();
+    ///
+    /// *data.make_mut() += 1;
+    /// assert_eq!(*data.get(), 2);
+    /// assert_eq!(*other.get(), 1);
+    /// ```
+    pub fn make_mut(&mut self) -> GcGuardMut<'_, T>
+    where
+        T: Clone + 'static,
+    {
+        loop {
+            if let Some(warrant) = COLLECTOR.get_unique_data_warrant(&self.backing_handle) {
+                return GcGuardMut {
+                    gc_ptr: self,
+                    _warrant: warrant,
+                };
+            }
+
+            // (A fresh `Gc` is almost always unique, unless `live_objects` found it already)
+            let cloned = Self::new(self.get().clone());
+            drop(mem::replace(self, cloned));
+        }
+    }
+
+    /// A new `Gc` for every tracked allocation of a `T` (see `shredder::live_objects`)
+    pub(crate) fn live_objects() -> Vec<Self>
+    where
+        T: Send + Sync + 'static,
+    {
+        COLLECTOR
+            .live_objects::<T>()
+            .into_iter()
+            .map(|(handle, ptr)| Self {
+                backing_handle: handle,
+                direct_ptr: ptr,
+            })
+            .collect()
+    }
+}
+
+impl<T: Scan + ?Sized> Clone for Gc<T> {
     #[must_use]
     fn clone(&self) -> Self {
         let new_handle = COLLECTOR.clone_handle(&self.backing_handle);
@@ -107,12 +557,12 @@ impl<T: Scan> Clone for Gc<T> {
 }
 
 // Same bounds as Arc<T>
-unsafe impl<T: Scan> Sync for Gc<T> where T: Sync + Send {}
-unsafe impl<T: Scan> Send for Gc<T> where T: Sync + Send {}
+unsafe impl<T: Scan + ?Sized> Sync for Gc<T> where T: Sync + Send {}
+unsafe impl<T: Scan + ?Sized> Send for Gc<T> where T: Sync + Send {}
 // Since we can clone Gc<T>, being able to send a Gc<T> implies possible sharing between threads
 // (Thus for Gc<T> to be send, T must be Send and Sync)
 
-impl<T: Scan> Drop for Gc<T> {
+impl<T: Scan + ?Sized> Drop for Gc<T> {
     fn drop(&mut self) {
         self.backing_handle.invalidate();
     }
@@ -121,7 +571,7 @@ impl<T: Scan> Drop for Gc<T> {
 // TODO: Implement GRwLock along the same lines
 
 // Lots of traits it's good for a smart ptr to implement:
-impl<T: Scan> Debug for Gc<T> {
+impl<T: Scan + ?Sized> Debug for Gc<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("Gc")
             .field("backing_handle", &"<SNIP>")
@@ -141,7 +591,46 @@ where
     }
 }
 
-impl<T: Scan> Display for Gc<T>
+// Slices get a single allocation, with the elements stored inline
+impl<T: Scan + 'static> From<Vec<T>> for Gc<[T]> {
+    fn from(v: Vec<T>) -> Self {
+        let (handle, ptr) = COLLECTOR.track_slice_with_drop(v);
+        Self {
+            backing_handle: handle,
+            direct_ptr: ptr,
+        }
+    }
+}
+
+impl<T: Scan + Clone + 'static> From<&[T]> for Gc<[T]> {
+    fn from(v: &[T]) -> Self {
+        Self::from(v.to_vec())
+    }
+}
+
+impl<T: Scan + 'static> FromIterator<T> for Gc<[T]> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl From<String> for Gc<str> {
+    fn from(v: String) -> Self {
+        let (handle, ptr) = COLLECTOR.track_str_with_drop(v);
+        Self {
+            backing_handle: handle,
+            direct_ptr: ptr,
+        }
+    }
+}
+
+impl From<&str> for Gc<str> {
+    fn from(v: &str) -> Self {
+        Self::from(v.to_owned())
+    }
+}
+
+impl<T: Scan + ?Sized> Display for Gc<T>
 where
     T: Display,
 {
@@ -151,15 +640,15 @@ where
     }
 }
 
-impl<T: Scan> fmt::Pointer for Gc<T> {
+impl<T: Scan + ?Sized> fmt::Pointer for Gc<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         fmt::Pointer::fmt(&self.direct_ptr, f)
     }
 }
 
-impl<T: Scan> Eq for Gc<T> where T: Eq {}
+impl<T: Scan + ?Sized> Eq for Gc<T> where T: Eq {}
 
-impl<T: Scan> Hash for Gc<T>
+impl<T: Scan + ?Sized> Hash for Gc<T>
 where
     T: Hash,
 {
@@ -168,7 +657,7 @@ where
     }
 }
 
-impl<T: Scan> Ord for Gc<T>
+impl<T: Scan + ?Sized> Ord for Gc<T>
 where
     T: Ord,
 {
@@ -182,7 +671,7 @@ where
 }
 
 #[allow(clippy::partialeq_ne_impl)]
-impl<T: Scan> PartialEq for Gc<T>
+impl<T: Scan + ?Sized> PartialEq for Gc<T>
 where
     T: PartialEq,
 {
@@ -201,7 +690,7 @@ where
     }
 }
 
-impl<T: Scan> PartialOrd for Gc<T>
+impl<T: Scan + ?Sized> PartialOrd for Gc<T>
 where
     T: PartialOrd,
 {
@@ -246,14 +735,76 @@ where
     }
 }
 
+/// Frees a `Gc::new_cyclic` allocation if its constructor panics
+struct AbandonOnUnwind<'a>(&'a InternalGcRef);
+
+impl Drop for AbandonOnUnwind<'_> {
+    fn drop(&mut self) {
+        // Safety: This is only dropped if `new_cyclic` didn't initialize the allocation
+        unsafe { COLLECTOR.abandon_uninit(self.0) };
+    }
+}
+
 /// A guard object that lets you access the underlying data of a `Gc`.
 /// It exists as data needs protection from being scanned while it's being concurrently modified.
-pub struct GcGuard<'a, T: Scan> {
+pub struct GcGuard<'a, T: Scan + ?Sized> {
     gc_ptr: &'a Gc<T>,
     _warrant: GcGuardWarrant,
 }
 
-impl<'a, T: Scan> Deref for GcGuard<'a, T> {
+impl<'a, T: Scan + ?Sized> GcGuard<'a, T> {
+    // (An associated function, so it can't shadow a method on `T`)
+    pub(crate) fn gc_of(this: &Self) -> &'a Gc<T> {
+        this.gc_ptr
+    }
+
+    /// Get the guarded data for all of `'a`, rather than just as long as this guard is borrowed
+    ///
+    /// Safety: The result must not be used after this guard (and so its warrant) is dropped
+    pub(crate) unsafe fn data_for_lifetime(this: &Self) -> &'a T {
+        &*this.gc_ptr.direct_ptr
+    }
+
+    /// Make a new guard for part of the guarded data, like `Ref::map`. This is an associated
+    /// function, so it doesn't conflict with methods on `T`.
+    ///
+    /// # Example
+    /// ```
+    /// use shredder::{Gc, GcGuard, Scan};
+    /// use shredder::wrappers::GcMappedGuard;
+    ///
+    /// #[derive(Scan)]
+    /// struct Person {
+    ///     name: String,
+    /// }
+    ///
+    /// fn name(person: &Gc<Person>) -> GcMappedGuard<'_, str> {
+    ///     GcGuard::map(person.get(), |p| p.name.as_str())
+    /// }
+    ///
+    /// let person = Gc::new(Person { name: String::from("Ada") });
+    /// assert_eq!(&*name(&person), "Ada");
+    /// ```
+    pub fn map<U: ?Sized, F>(this: Self, f: F) -> GcMappedGuard<'a, U>
+    where
+        F: FnOnce(&T) -> &U,
+    {
+        GcMappedGuard::new(this, f)
+    }
+
+    /// Make a new guard for part of the guarded data, or give back this guard if `f` returns `None`
+    ///
+    /// # Errors
+    /// Returns the original guard if `f` returns `None`
+    pub fn filter_map<U: ?Sized, F>(this: Self, f: F) -> Result<GcMappedGuard<'a, U>, Self>
+    where
+        F: FnOnce(&T) -> Option<&U>,
+    {
+        GcMappedGuard::try_new(this, f)
+    }
+}
+
+impl<'a, T: Scan + ?Sized> Deref for GcGuard<'a, T> {
     type Target = T;
 
     #[must_use]
@@ -263,26 +814,121 @@ impl<'a, T: Scan> Deref for GcGuard<'a, T> {
 }
 
 /// It is impossible for the value behind a `GcGuard` to move (since it's basically a `&T`)
-unsafe impl<'a, T: Scan> StableDeref for GcGuard<'a, T> {}
+unsafe impl<'a, T: Scan + ?Sized> StableDeref for GcGuard<'a, T> {}
 
-impl<'a, T: Scan> AsRef<T> for GcGuard<'a, T> {
+impl<'a, T: Scan + ?Sized> AsRef<T> for GcGuard<'a, T> {
     #[must_use]
     fn as_ref(&self) -> &T {
         self.deref()
     }
 }
 
-impl<'a, T: Scan> Borrow<T> for GcGuard<'a, T> {
+impl<'a, T: Scan + ?Sized> Borrow<T> for GcGuard<'a, T> {
     #[must_use]
     fn borrow(&self) -> &T {
         self.deref()
     }
 }
 
-impl<'a, T: Scan + Debug> Debug for GcGuard<'a, T> {
+impl<'a, T: Scan + Debug + ?Sized> Debug for GcGuard<'a, T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("GcGuard")
-            .field("v", self.deref())
+            .field("v", &self.deref())
+            .field("warrant", &"<SNIP>")
+            .finish()
+    }
+}
+
+/// Like a `GcGuard`, but holding its own clone of the `Gc` instead of borrowing it.
+/// Returned by `Gc::get_owned`.
+pub struct OwnedGcGuard<T: Scan + ?Sized> {
+    // (Fields are dropped in order, so the warrant is released before the `Gc`)
+    _warrant: GcGuardWarrant,
+    gc: Gc<T>,
+}
+
+impl<T: Scan + ?Sized> Deref for OwnedGcGuard<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.gc.direct_ptr }
+    }
+}
+
+/// It is impossible for the value behind an `OwnedGcGuard` to move, since it lives in the `Gc`
+unsafe impl<T: Scan + ?Sized> StableDeref for OwnedGcGuard<T> {}
+
+impl<T: Scan + ?Sized> AsRef<T> for OwnedGcGuard<T> {
+    fn as_ref(&self) -> &T {
+        self.deref()
+    }
+}
+
+impl<T: Scan + ?Sized> Borrow<T> for OwnedGcGuard<T> {
+    fn borrow(&self) -> &T {
+        self.deref()
+    }
+}
+
+impl<T: Scan + Debug + ?Sized> Debug for OwnedGcGuard<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnedGcGuard")
+            .field("v", &self.deref())
+            .field("warrant", &"<SNIP>")
+            .finish()
+    }
+}
+
+/// A guard object that lets you mutate the underlying data of a `Gc`, returned by `get_mut` and
+/// `make_mut`. While it exists, no other `Gc` can point at the data.
+pub struct GcGuardMut<'a, T: Scan + ?Sized> {
+    gc_ptr: &'a mut Gc<T>,
+    _warrant: GcGuardExclusiveWarrant,
+}
+
+impl<'a, T: Scan + ?Sized> GcGuardMut<'a, T> {
+    /// Make a new guard for part of the guarded data, like `RefMut::map`
+    pub fn map<U: ?Sized, F>(this: Self, f: F) -> GcMappedGuardMut<'a, U>
+    where
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        GcMappedGuardMut::new(this, f)
+    }
+
+    /// Make a new guard for part of the guarded data, or give back this guard if `f` returns `None`
+    ///
+    /// # Errors
+    /// Returns the original guard if `f` returns `None`
+    pub fn filter_map<U: ?Sized, F>(this: Self, f: F) -> Result<GcMappedGuardMut<'a, U>, Self>
+    where
+        F: FnOnce(&mut T) -> Option<&mut U>,
+    {
+        GcMappedGuardMut::try_new(this, f)
+    }
+}
+
+/// The data behind a `GcGuardMut` lives in the `Gc`, so it can't move
+unsafe impl<T: Scan + ?Sized> StableDeref for GcGuardMut<'_, T> {}
+
+impl<T: Scan + ?Sized> Deref for GcGuardMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.gc_ptr.direct_ptr }
+    }
+}
+
+impl<T: Scan + ?Sized> DerefMut for GcGuardMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: We hold the only `Gc` to this data, plus a warrant so it can't be scanned
+        unsafe { &mut *self.gc_ptr.direct_ptr.cast_mut() }
+    }
+}
+
+impl<T: Scan + Debug + ?Sized> Debug for GcGuardMut<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcGuardMut")
+            .field("v", &self.deref())
             .field("warrant", &"<SNIP>")
             .finish()
     }
@@ -328,6 +974,40 @@ impl<T: Scan + 'static> Gc<RefCell<T>> {
         let g = self.get();
         GcRefMut::try_borrow_mut(g)
     }
+
+    /// Like `borrow`, but returns an error instead of panicking if the `Gc` data can't be accessed
+    /// (see `checked_get`)
+    ///
+    /// # Errors
+    /// Returns a `GcError` if `checked_get` would
+    pub fn checked_borrow(&self) -> Result<GcRef<'_, T>, GcError> {
+        let g = self.checked_get()?;
+        Ok(GcRef::borrow(g))
+    }
+
+    /// Like `borrow_mut`, but returns an error instead of panicking if the `Gc` data can't be
+    /// accessed (see `checked_get`)
+    ///
+    /// # Errors
+    /// Returns a `GcError` if `checked_get` would
+    pub fn checked_borrow_mut(&self) -> Result<GcRefMut<'_, T>, GcError> {
+        let g = self.checked_get()?;
+        Ok(GcRefMut::borrow_mut(g))
+    }
+
+    /// Like `borrow`, but the guard holds its own clone of this `Gc` (see `get_owned`)
+    #[must_use]
+    pub fn borrow_owned(&self) -> OwnedGcRef<T> {
+        let g = self.get_owned();
+        OwnedGcRef::borrow(g)
+    }
+
+    /// Like `borrow_mut`, but the guard holds its own clone of this `Gc` (see `get_owned`)
+    #[must_use]
+    pub fn borrow_mut_owned(&self) -> OwnedGcRefMut<T> {
+        let g = self.get_owned();
+        OwnedGcRefMut::borrow_mut(g)
+    }
 }
 
 impl<T: Scan + 'static> Gc<sync::Mutex<T>> {
@@ -353,6 +1033,42 @@ impl<T: Scan + 'static> Gc<sync::Mutex<T>> {
         let g = self.get();
         GcMutexGuard::try_lock(g)
     }
+
+    /// Like `lock`, but returns an error instead of panicking if the `Gc` data can't be accessed
+    /// (see `checked_get`)
+    ///
+    /// # Errors
+    /// Returns a `GcLockError`, which either wraps the `GcError` from `checked_get` or a
+    /// `GcPoisonError` if the mutex was poisoned
+    pub fn checked_lock(&self) -> Result<GcMutexGuard<'_, T>, GcLockError<GcMutexGuard<'_, T>>> {
+        let g = self.checked_get().map_err(GcLockError::Gc)?;
+        GcMutexGuard::lock(g).map_err(GcLockError::Poisoned)
+    }
+
+    /// Like `lock`, but gives up after `timeout`
+    ///
+    /// A `std` `Mutex` can't be waited on with a timeout, so while the lock is held this retries
+    /// (with a short sleep between attempts) until the timeout runs out.
+    ///
+    /// # Errors
+    /// Returns a `GcLockTimeoutError`, which says whether the collector or another lock holder
+    /// made us time out. (Or wraps a `GcPoisonError` if the mutex was poisoned.)
+    pub fn lock_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<GcMutexGuard<'_, T>, GcLockTimeoutError<GcMutexGuard<'_, T>>> {
+        lock_until(self, Instant::now() + timeout, GcMutexGuard::try_lock)
+    }
+
+    /// Like `lock`, but the guard holds its own clone of this `Gc` (see `get_owned`)
+    ///
+    /// # Errors
+    /// Returns a `GcPoisonError` if the underlying `.lock` method returns a poison error.
+    /// You may use `into_inner` in order to recover the guard from that error.
+    pub fn lock_owned(&self) -> Result<OwnedGcMutexGuard<T>, GcPoisonError<OwnedGcMutexGuard<T>>> {
+        let g = self.get_owned();
+        OwnedGcMutexGuard::lock(g)
+    }
 }
 
 impl<T: Scan + 'static> Gc<sync::RwLock<T>> {
@@ -409,4 +1125,349 @@ impl<T: Scan + 'static> Gc<sync::RwLock<T>> {
         let g = self.get();
         GcRwLockWriteGuard::try_write(g)
     }
+
+    /// Like `read`, but returns an error instead of panicking if the `Gc` data can't be accessed
+    /// (see `checked_get`)
+    ///
+    /// # Errors
+    /// Returns a `GcLockError`, which either wraps the `GcError` from `checked_get` or a
+    /// `GcPoisonError` if the lock was poisoned
+    pub fn checked_read(
+        &self,
+    ) -> Result<GcRwLockReadGuard<'_, T>, GcLockError<GcRwLockReadGuard<'_, T>>> {
+        let g = self.checked_get().map_err(GcLockError::Gc)?;
+        GcRwLockReadGuard::read(g).map_err(GcLockError::Poisoned)
+    }
+
+    /// Like `write`, but returns an error instead of panicking if the `Gc` data can't be accessed
+    /// (see `checked_get`)
+    ///
+    /// # Errors
+    /// Returns a `GcLockError`, which either wraps the `GcError` from `checked_get` or a
+    /// `GcPoisonError` if the lock was poisoned
+    pub fn checked_write(
+        &self,
+    ) -> Result<GcRwLockWriteGuard<'_, T>, GcLockError<GcRwLockWriteGuard<'_, T>>> {
+        let g = self.checked_get().map_err(GcLockError::Gc)?;
+        GcRwLockWriteGuard::write(g).map_err(GcLockError::Poisoned)
+    }
+
+    /// Like `read`, but gives up after `timeout`
+    ///
+    /// A `std` `RwLock` can't be waited on with a timeout, so while the lock is held this retries
+    /// (with a short sleep between attempts) until the timeout runs out.
+    ///
+    /// # Errors
+    /// Returns a `GcLockTimeoutError`, which says whether the collector or another lock holder
+    /// made us time out. (Or wraps a `GcPoisonError` if the lock was poisoned.)
+    pub fn read_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<GcRwLockReadGuard<'_, T>, GcLockTimeoutError<GcRwLockReadGuard<'_, T>>> {
+        lock_until(self, Instant::now() + timeout, GcRwLockReadGuard::try_read)
+    }
+
+    /// Like `write`, but gives up after `timeout`
+    ///
+    /// A `std` `RwLock` can't be waited on with a timeout, so while the lock is held this retries
+    /// (with a short sleep between attempts) until the timeout runs out.
+    ///
+    /// # Errors
+    /// Returns a `GcLockTimeoutError`, which says whether the collector or another lock holder
+    /// made us time out. (Or wraps a `GcPoisonError` if the lock was poisoned.)
+    pub fn write_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<GcRwLockWriteGuard<'_, T>, GcLockTimeoutError<GcRwLockWriteGuard<'_, T>>> {
+        lock_until(
+            self,
+            Instant::now() + timeout,
+            GcRwLockWriteGuard::try_write,
+        )
+    }
+
+    /// Like `read`, but the guard holds its own clone of this `Gc` (see `get_owned`)
+    ///
+    /// # Errors
+    /// Returns a `GcPoisonError` if the underlying `read` method returns a poison error.
+    /// You may use `into_inner` in order to recover the guard from that error.
+    pub fn read_owned(
+        &self,
+    ) -> Result<OwnedGcRwLockReadGuard<T>, GcPoisonError<OwnedGcRwLockReadGuard<T>>> {
+        let g = self.get_owned();
+        OwnedGcRwLockReadGuard::read(g)
+    }
+
+    /// Like `write`, but the guard holds its own clone of this `Gc` (see `get_owned`)
+    ///
+    /// # Errors
+    /// Returns a `GcPoisonError` if the underlying `write` method returns a poison error.
+    /// You may use `into_inner` in order to recover the guard from that error.
+    pub fn write_owned(
+        &self,
+    ) -> Result<OwnedGcRwLockWriteGuard<T>, GcPoisonError<OwnedGcRwLockWriteGuard<T>>> {
+        let g = self.get_owned();
+        OwnedGcRwLockWriteGuard::write(g)
+    }
+}
+
+impl<T: Scan + 'static> Gc<parking_lot::Mutex<T>> {
+    /// Call the underlying `lock` method on the inner `parking_lot::Mutex`
+    ///
+    /// This is just a nice method so you don't have to `get` manually
+    #[must_use]
+    pub fn lock(&self) -> GcParkingLotMutexGuard<'_, T> {
+        let g = self.get();
+        GcParkingLotMutexGuard::lock(g)
+    }
+
+    /// Call the underlying `try_lock` method on the inner `parking_lot::Mutex`
+    ///
+    /// This is just a nice method so you don't have to `get` manually
+    #[must_use]
+    pub fn try_lock(&self) -> Option<GcParkingLotMutexGuard<'_, T>> {
+        let g = self.get();
+        GcParkingLotMutexGuard::try_lock(g)
+    }
+
+    /// Like `lock`, but returns an error instead of panicking if the `Gc` data can't be accessed
+    /// (see `checked_get`)
+    ///
+    /// # Errors
+    /// Returns a `GcError` if `checked_get` would
+    pub fn checked_lock(&self) -> Result<GcParkingLotMutexGuard<'_, T>, GcError> {
+        let g = self.checked_get()?;
+        Ok(GcParkingLotMutexGuard::lock(g))
+    }
+}
+
+impl<T: Scan + 'static> Gc<parking_lot::RwLock<T>> {
+    /// Call the underlying `read` method on the inner `parking_lot::RwLock`
+    ///
+    /// This is just a nice method so you don't have to `get` manually
+    #[must_use]
+    pub fn read(&self) -> GcParkingLotRwLockReadGuard<'_, T> {
+        let g = self.get();
+        GcParkingLotRwLockReadGuard::read(g)
+    }
+
+    /// Call the underlying `write` method on the inner `parking_lot::RwLock`
+    ///
+    /// This is just a nice method so you don't have to `get` manually
+    #[must_use]
+    pub fn write(&self) -> GcParkingLotRwLockWriteGuard<'_, T> {
+        let g = self.get();
+        GcParkingLotRwLockWriteGuard::write(g)
+    }
+
+    /// Call the underlying `try_read` method on the inner `parking_lot::RwLock`
+    ///
+    /// This is just a nice method so you don't have to `get` manually
+    #[must_use]
+    pub fn try_read(&self) -> Option<GcParkingLotRwLockReadGuard<'_, T>> {
+        let g = self.get();
+        GcParkingLotRwLockReadGuard::try_read(g)
+    }
+
+    /// Call the underlying `try_write` method on the inner `parking_lot::RwLock`
+    ///
+    /// This is just a nice method so you don't have to `get` manually
+    #[must_use]
+    pub fn try_write(&self) -> Option<GcParkingLotRwLockWriteGuard<'_, T>> {
+        let g = self.get();
+        GcParkingLotRwLockWriteGuard::try_write(g)
+    }
+
+    /// Like `read`, but returns an error instead of panicking if the `Gc` data can't be accessed
+    /// (see `checked_get`)
+    ///
+    /// # Errors
+    /// Returns a `GcError` if `checked_get` would
+    pub fn checked_read(&self) -> Result<GcParkingLotRwLockReadGuard<'_, T>, GcError> {
+        let g = self.checked_get()?;
+        Ok(GcParkingLotRwLockReadGuard::read(g))
+    }
+
+    /// Like `write`, but returns an error instead of panicking if the `Gc` data can't be accessed
+    /// (see `checked_get`)
+    ///
+    /// # Errors
+    /// Returns a `GcError` if `checked_get` would
+    pub fn checked_write(&self) -> Result<GcParkingLotRwLockWriteGuard<'_, T>, GcError> {
+        let g = self.checked_get()?;
+        Ok(GcParkingLotRwLockWriteGuard::write(g))
+    }
+
+    /// Call the underlying `upgradable_read` method on the inner `parking_lot::RwLock`
+    ///
+    /// The guard can later be upgraded to a write guard, and holds onto the `Gc` data throughout.
+    ///
+    /// # Example
+    /// ```
+    /// use shredder::Gc;
+    /// use shredder::wrappers::GcParkingLotRwLockUpgradableReadGuard;
+    ///
+    /// let cache = Gc::new(parking_lot::RwLock::new(None));
+    ///
+    /// let guard = cache.upgradable_read();
+    /// if guard.is_none() {
+    ///     let mut guard = GcParkingLotRwLockUpgradableReadGuard::upgrade(guard);
+    ///     *guard = Some(42);
+    /// }
+    /// assert_eq!(*cache.read(), Some(42));
+    /// ```
+    #[must_use]
+    pub fn upgradable_read(&self) -> GcParkingLotRwLockUpgradableReadGuard<'_, T> {
+        let g = self.get();
+        GcParkingLotRwLockUpgradableReadGuard::upgradable_read(g)
+    }
+
+    /// Call the underlying `try_upgradable_read` method on the inner `parking_lot::RwLock`
+    ///
+    /// This is just a nice method so you don't have to `get` manually
+    #[must_use]
+    pub fn try_upgradable_read(&self) -> Option<GcParkingLotRwLockUpgradableReadGuard<'_, T>> {
+        let g = self.get();
+        GcParkingLotRwLockUpgradableReadGuard::try_upgradable_read(g)
+    }
+}
+
+impl<T: GcAny> Gc<T> {
+    /// Forget the type of this data, so `Gc`s of different types can be stored together. Use
+    /// `downcast` to get the original `Gc` back.
+    #[must_use]
+    pub fn into_any(self) -> Gc<dyn GcAny> {
+        let this = mem::ManuallyDrop::new(self);
+        // Safety: `this` is never dropped, so moving the handle out doesn't duplicate it
+        let handle = unsafe { ptr::read(ptr::from_ref(&this.backing_handle)) };
+        let direct_ptr: *const dyn GcAny = this.direct_ptr;
+        Gc {
+            backing_handle: handle,
+            direct_ptr,
+        }
+    }
+}
+
+impl Gc<dyn GcAny> {
+    /// Returns `true` if the data in this `Gc` is a `T`, or `false` if it isn't (or has been
+    /// deallocated)
+    ///
+    /// This checks the type recorded when the data was allocated. Some data doesn't have one: data
+    /// from `new_no_drop`, `new_with_finalizer` or a `Scope`, and boxed `dyn GcAny`s given to
+    /// `from_box`. That data is briefly accessed instead (like `checked_get`), so it may block while
+    /// the data is scanned.
+    #[must_use]
+    pub fn is<T: GcAny>(&self) -> bool {
+        match self.recorded_type_id() {
+            Some(type_id) => type_id == TypeId::of::<T>() && self.is_live(),
+            None => self.checked_get().is_ok_and(|guard| {
+                let data: &dyn GcAny = &*guard;
+                data.type_id() == TypeId::of::<T>()
+            }),
+        }
+    }
+
+    /// Attempt to turn this into a `Gc<T>`, like `Box::<dyn Any>::downcast`
+    ///
+    /// # Example
+    /// ```
+    /// use shredder::{Gc, GcAny};
+    ///
+    /// let objects: Vec<Gc<dyn GcAny>> = vec![Gc::new(7_u32).into_any(), Gc::new(String::from("hi")).into_any()];
+    /// let numbers: Vec<Gc<u32>> = objects
+    ///     .into_iter()
+    ///     .filter_map(|o| o.downcast().ok())
+    ///     .collect();
+    /// assert_eq!(*numbers[0].get(), 7);
+    /// ```
+    ///
+    /// # Errors
+    /// Returns this `Gc` back if the data isn't a `T` (or has been deallocated)
+    pub fn downcast<T: GcAny>(self) -> Result<Gc<T>, Self> {
+        if !self.is::<T>() {
+            return Err(self);
+        }
+
+        let this = mem::ManuallyDrop::new(self);
+        // Safety: `this` is never dropped, so moving the handle out doesn't duplicate it
+        let handle = unsafe { ptr::read(ptr::from_ref(&this.backing_handle)) };
+        Ok(Gc {
+            backing_handle: handle,
+            direct_ptr: this.direct_ptr.cast::<T>(),
+        })
+    }
+}
+
+impl<T: GcAny + Send + Sync> Gc<T> {
+    /// Like `into_any`, but the result can still be sent between threads
+    ///
+    /// # Example
+    /// ```
+    /// use std::thread;
+    ///
+    /// use shredder::{Gc, GcAny};
+    ///
+    /// let object: Gc<dyn GcAny + Send + Sync> = Gc::new(7_u32).into_any_sync();
+    /// let number = thread::spawn(move || object.downcast::<u32>().ok()).join().unwrap();
+    /// assert_eq!(*number.unwrap().get(), 7);
+    /// ```
+    #[must_use]
+    pub fn into_any_sync(self) -> Gc<dyn GcAny + Send + Sync> {
+        let this = mem::ManuallyDrop::new(self);
+        // Safety: `this` is never dropped, so moving the handle out doesn't duplicate it
+        let handle = unsafe { ptr::read(ptr::from_ref(&this.backing_handle)) };
+        let direct_ptr: *const (dyn GcAny + Send + Sync) = this.direct_ptr;
+        Gc {
+            backing_handle: handle,
+            direct_ptr,
+        }
+    }
+}
+
+impl Gc<dyn GcAny + Send + Sync> {
+    /// Returns `true` if the data in this `Gc` is a `T` (see `Gc::<dyn GcAny>::is`)
+    #[must_use]
+    pub fn is<T: GcAny>(&self) -> bool {
+        match self.recorded_type_id() {
+            Some(type_id) => type_id == TypeId::of::<T>() && self.is_live(),
+            None => self.checked_get().is_ok_and(|guard| {
+                let data: &dyn GcAny = &*guard;
+                data.type_id() == TypeId::of::<T>()
+            }),
+        }
+    }
+
+    /// Attempt to turn this into a `Gc<T>` (see `Gc::<dyn GcAny>::downcast`)
+    ///
+    /// # Errors
+    /// Returns this `Gc` back if the data isn't a `T` (or has been deallocated)
+    pub fn downcast<T: GcAny + Send + Sync>(self) -> Result<Gc<T>, Self> {
+        if !self.is::<T>() {
+            return Err(self);
+        }
+
+        let this = mem::ManuallyDrop::new(self);
+        // Safety: `this` is never dropped, so moving the handle out doesn't duplicate it
+        let handle = unsafe { ptr::read(ptr::from_ref(&this.backing_handle)) };
+        Ok(Gc {
+            backing_handle: handle,
+            direct_ptr: this.direct_ptr.cast::<T>(),
+        })
+    }
+}
+
+impl<T: Scan> Gc<GcAsyncMutex<T>> {
+    /// Lock the inner `GcAsyncMutex`. The returned future waits without blocking the thread.
+    ///
+    /// # Example
+    /// ```
+    /// use shredder::{Gc, GcAsyncMutex};
+    ///
+    /// async fn increment(counter: &Gc<GcAsyncMutex<u32>>) {
+    ///     *counter.lock_async().await += 1;
+    /// }
+    /// ```
+    pub fn lock_async(&self) -> GcAsyncMutexLockFuture<'_, T> {
+        GcAsyncMutexLockFuture { gc: self }
+    }
 }
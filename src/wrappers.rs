@@ -1,14 +1,203 @@
 use std::cell::{BorrowError, BorrowMutError, RefCell};
+use std::cmp;
 use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::ptr;
 use std::sync::{self, TryLockError};
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::{GcGuard, Scan};
+use parking_lot::{MutexGuard, RwLockReadGuard, RwLockUpgradableReadGuard, RwLockWriteGuard};
+use stable_deref_trait::StableDeref;
+
+use crate::collector::COLLECTOR;
+use crate::{Gc, GcAsyncMutex, GcGuard, OwnedGcGuard, Scan};
+use crate::{GcError, PoisonPolicy};
+
+/// Lets us hold onto any guard, while forgetting its type
+trait ErasedGuard {}
+impl<T> ErasedGuard for T {}
+
+/// A guard that has been projected onto part of the data it guarded, with `map` or `filter_map`.
+/// (Like `std::cell::Ref::map`, but for any of our guards.)
+///
+/// The original guard is kept alive inside, so the data is still protected from being scanned.
+pub struct GcMappedGuard<'a, U: ?Sized> {
+    guard: Box<dyn ErasedGuard + 'a>,
+    ptr: *const U,
+}
+
+impl<'a, U: ?Sized> GcMappedGuard<'a, U> {
+    pub(crate) fn new<G, F>(guard: G, f: F) -> Self
+    where
+        G: StableDeref + 'a,
+        F: FnOnce(&G::Target) -> &U,
+    {
+        // `G: StableDeref`, so `ptr` stays valid when the guard is moved into the box
+        let ptr: *const U = f(&*guard);
+        Self {
+            guard: Box::new(guard),
+            ptr,
+        }
+    }
+
+    pub(crate) fn try_new<G, F>(guard: G, f: F) -> Result<Self, G>
+    where
+        G: StableDeref + 'a,
+        F: FnOnce(&G::Target) -> Option<&U>,
+    {
+        match f(&*guard).map(ptr::from_ref) {
+            Some(ptr) => Ok(Self {
+                guard: Box::new(guard),
+                ptr,
+            }),
+            None => Err(guard),
+        }
+    }
+
+    /// Project this guard further, onto part of what it points at
+    pub fn map<V: ?Sized, F>(this: Self, f: F) -> GcMappedGuard<'a, V>
+    where
+        F: FnOnce(&U) -> &V,
+    {
+        let ptr: *const V = f(&*this);
+        GcMappedGuard {
+            guard: this.guard,
+            ptr,
+        }
+    }
+
+    /// Project this guard further, or give it back if `f` returns `None`
+    ///
+    /// # Errors
+    /// Returns the original guard if `f` returns `None`
+    pub fn filter_map<V: ?Sized, F>(this: Self, f: F) -> Result<GcMappedGuard<'a, V>, Self>
+    where
+        F: FnOnce(&U) -> Option<&V>,
+    {
+        match f(&*this).map(ptr::from_ref) {
+            Some(ptr) => Ok(GcMappedGuard {
+                guard: this.guard,
+                ptr,
+            }),
+            None => Err(this),
+        }
+    }
+}
+
+impl<U: ?Sized> Deref for GcMappedGuard<'_, U> {
+    type Target = U;
+
+    fn deref(&self) -> &Self::Target {
+        // Safety: The guard this points into is still alive
+        unsafe { &*self.ptr }
+    }
+}
+
+impl<U: Debug + ?Sized> Debug for GcMappedGuard<'_, U> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcMappedGuard")
+            .field("v", &self.deref())
+            .finish()
+    }
+}
+
+/// Like `GcMappedGuard`, but projected from a guard that allows mutation
+pub struct GcMappedGuardMut<'a, U: ?Sized> {
+    guard: Box<dyn ErasedGuard + 'a>,
+    ptr: *mut U,
+}
+
+impl<'a, U: ?Sized> GcMappedGuardMut<'a, U> {
+    pub(crate) fn new<G, F>(mut guard: G, f: F) -> Self
+    where
+        G: StableDeref + DerefMut + 'a,
+        F: FnOnce(&mut G::Target) -> &mut U,
+    {
+        // `G: StableDeref`, so `ptr` stays valid when the guard is moved into the box
+        let ptr: *mut U = f(&mut *guard);
+        Self {
+            guard: Box::new(guard),
+            ptr,
+        }
+    }
+
+    pub(crate) fn try_new<G, F>(mut guard: G, f: F) -> Result<Self, G>
+    where
+        G: StableDeref + DerefMut + 'a,
+        F: FnOnce(&mut G::Target) -> Option<&mut U>,
+    {
+        match f(&mut *guard).map(ptr::from_mut) {
+            Some(ptr) => Ok(Self {
+                guard: Box::new(guard),
+                ptr,
+            }),
+            None => Err(guard),
+        }
+    }
+
+    /// Project this guard further, onto part of what it points at
+    pub fn map<V: ?Sized, F>(mut this: Self, f: F) -> GcMappedGuardMut<'a, V>
+    where
+        F: FnOnce(&mut U) -> &mut V,
+    {
+        let ptr: *mut V = f(&mut *this);
+        GcMappedGuardMut {
+            guard: this.guard,
+            ptr,
+        }
+    }
+
+    /// Project this guard further, or give it back if `f` returns `None`
+    ///
+    /// # Errors
+    /// Returns the original guard if `f` returns `None`
+    pub fn filter_map<V: ?Sized, F>(mut this: Self, f: F) -> Result<GcMappedGuardMut<'a, V>, Self>
+    where
+        F: FnOnce(&mut U) -> Option<&mut V>,
+    {
+        match f(&mut *this).map(ptr::from_mut) {
+            Some(ptr) => Ok(GcMappedGuardMut {
+                guard: this.guard,
+                ptr,
+            }),
+            None => Err(this),
+        }
+    }
+}
+
+impl<U: ?Sized> Deref for GcMappedGuardMut<'_, U> {
+    type Target = U;
+
+    fn deref(&self) -> &Self::Target {
+        // Safety: The guard this points into is still alive
+        unsafe { &*self.ptr }
+    }
+}
+
+impl<U: ?Sized> DerefMut for GcMappedGuardMut<'_, U> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: The guard this points into is still alive, and allowed mutation
+        unsafe { &mut *self.ptr }
+    }
+}
+
+impl<U: Debug + ?Sized> Debug for GcMappedGuardMut<'_, U> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcMappedGuardMut")
+            .field("v", &self.deref())
+            .finish()
+    }
+}
 
 // This is special casing for Gc<RefCell<T>>
 rental! {
     mod gc_refcell_internals {
-        use crate::{Scan, GcGuard};
+        use crate::{Scan, GcGuard, OwnedGcGuard};
         use std::cell::{Ref, RefCell, RefMut};
 
         /// Self referential wrapper around `Ref` for ergonomics
@@ -24,6 +213,20 @@ rental! {
             gc_guard: GcGuard<'a, RefCell<T>>,
             cell_ref: RefMut<'gc_guard, T>
         }
+
+        /// Self referential wrapper around `Ref`, holding its own `Gc`
+        #[rental(deref_suffix)]
+        pub struct OwnedGcRefInt<T: Scan + 'static> {
+            gc_guard: OwnedGcGuard<RefCell<T>>,
+            cell_ref: Ref<'gc_guard, T>
+        }
+
+        /// Self referential wrapper around `RefMut`, holding its own `Gc`
+        #[rental(deref_mut_suffix)]
+        pub struct OwnedGcRefMutInt<T: Scan + 'static> {
+            gc_guard: OwnedGcGuard<RefCell<T>>,
+            cell_ref: RefMut<'gc_guard, T>
+        }
     }
 }
 
@@ -50,8 +253,30 @@ impl<'a, T: Scan + 'static> GcRef<'a, T> {
 
         Ok(Self { internal_ref })
     }
+
+    /// Make a new guard for part of the borrowed data, like `Ref::map`
+    pub fn map<U: ?Sized, F>(this: Self, f: F) -> GcMappedGuard<'a, U>
+    where
+        F: FnOnce(&T) -> &U,
+    {
+        GcMappedGuard::new(this, f)
+    }
+
+    /// Make a new guard for part of the borrowed data, or give back this guard if `f` returns `None`
+    ///
+    /// # Errors
+    /// Returns the original guard if `f` returns `None`
+    pub fn filter_map<U: ?Sized, F>(this: Self, f: F) -> Result<GcMappedGuard<'a, U>, Self>
+    where
+        F: FnOnce(&T) -> Option<&U>,
+    {
+        GcMappedGuard::try_new(this, f)
+    }
 }
 
+/// The borrowed data lives in the `Gc`, so it can't move
+unsafe impl<T: Scan + 'static> StableDeref for GcRef<'_, T> {}
+
 impl<'a, T: Scan + 'static> Deref for GcRef<'a, T> {
     type Target = T;
 
@@ -77,8 +302,30 @@ impl<'a, T: Scan + 'static> GcRefMut<'a, T> {
 
         Ok(Self { internal_ref })
     }
+
+    /// Make a new guard for part of the borrowed data, like `RefMut::map`
+    pub fn map<U: ?Sized, F>(this: Self, f: F) -> GcMappedGuardMut<'a, U>
+    where
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        GcMappedGuardMut::new(this, f)
+    }
+
+    /// Make a new guard for part of the borrowed data, or give back this guard if `f` returns `None`
+    ///
+    /// # Errors
+    /// Returns the original guard if `f` returns `None`
+    pub fn filter_map<U: ?Sized, F>(this: Self, f: F) -> Result<GcMappedGuardMut<'a, U>, Self>
+    where
+        F: FnOnce(&mut T) -> Option<&mut U>,
+    {
+        GcMappedGuardMut::try_new(this, f)
+    }
 }
 
+/// The borrowed data lives in the `Gc`, so it can't move
+unsafe impl<T: Scan + 'static> StableDeref for GcRefMut<'_, T> {}
+
 impl<T: Scan + 'static + Debug> Debug for GcRefMut<'_, T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("GcRefMut")
@@ -101,6 +348,68 @@ impl<'a, T: Scan + 'static> DerefMut for GcRefMut<'a, T> {
     }
 }
 
+/// Like a `GcRef`, but holding its own clone of the `Gc` (returned by `Gc::borrow_owned`)
+pub struct OwnedGcRef<T: Scan + 'static> {
+    internal_ref: gc_refcell_internals::OwnedGcRefInt<T>,
+}
+
+impl<T: Scan + 'static> OwnedGcRef<T> {
+    pub(crate) fn borrow(g: OwnedGcGuard<RefCell<T>>) -> Self {
+        let internal_ref = gc_refcell_internals::OwnedGcRefInt::new(g, RefCell::borrow);
+        Self { internal_ref }
+    }
+}
+
+impl<T: Scan + 'static + Debug> Debug for OwnedGcRef<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnedGcRef")
+            .field("ref", self.deref())
+            .finish()
+    }
+}
+
+impl<T: Scan + 'static> Deref for OwnedGcRef<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.internal_ref.deref()
+    }
+}
+
+/// Like a `GcRefMut`, but holding its own clone of the `Gc` (returned by `Gc::borrow_mut_owned`)
+pub struct OwnedGcRefMut<T: Scan + 'static> {
+    internal_ref: gc_refcell_internals::OwnedGcRefMutInt<T>,
+}
+
+impl<T: Scan + 'static> OwnedGcRefMut<T> {
+    pub(crate) fn borrow_mut(g: OwnedGcGuard<RefCell<T>>) -> Self {
+        let internal_ref = gc_refcell_internals::OwnedGcRefMutInt::new(g, RefCell::borrow_mut);
+        Self { internal_ref }
+    }
+}
+
+impl<T: Scan + 'static + Debug> Debug for OwnedGcRefMut<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnedGcRefMut")
+            .field("ref", self.deref())
+            .finish()
+    }
+}
+
+impl<T: Scan + 'static> Deref for OwnedGcRefMut<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.internal_ref.deref()
+    }
+}
+
+impl<T: Scan + 'static> DerefMut for OwnedGcRefMut<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.internal_ref.deref_mut()
+    }
+}
+
 /// An error representing that the `Mutex` or `RwLock` you tried to lock was poisoned
 ///
 /// It contains a locked guard which you can recover with `into_inner`
@@ -125,13 +434,77 @@ pub enum GcTryLockError<T> {
     WouldBlock,
 }
 
+/// An error representing that you couldn't lock with `checked_lock` (or `checked_read` or
+/// `checked_write`)
+#[derive(Debug)]
+pub enum GcLockError<T> {
+    /// The lock was poisoned, so here is a `GcPoisonError`
+    Poisoned(GcPoisonError<T>),
+    /// The `Gc` data couldn't be accessed, so we never got to try the lock
+    Gc(GcError),
+}
+
+/// An error representing that you couldn't lock with `lock_timeout` (or `read_timeout` or
+/// `write_timeout`) before the timeout ran out
+#[derive(Debug)]
+pub enum GcLockTimeoutError<T> {
+    /// The lock was poisoned, so here is a `GcPoisonError`
+    Poisoned(GcPoisonError<T>),
+    /// The collector was scanning the data (or it couldn't be accessed at all), so we never got to
+    /// try the lock
+    Gc(GcError),
+    /// The lock was held (by user code) until the timeout
+    TimedOut,
+}
+
+// Keep trying to lock `gc` until `deadline`. (`std` locks don't support timeouts themselves)
+pub(crate) fn lock_until<'a, T, G, F>(
+    gc: &'a Gc<T>,
+    deadline: Instant,
+    try_lock: F,
+) -> Result<G, GcLockTimeoutError<G>>
+where
+    T: Scan + ?Sized,
+    F: Fn(GcGuard<'a, T>) -> Result<G, GcTryLockError<G>>,
+{
+    let mut attempts = 0_u32;
+    loop {
+        let g = gc.get_until(deadline).map_err(GcLockTimeoutError::Gc)?;
+        match try_lock(g) {
+            Ok(guard) => return Ok(guard),
+            Err(GcTryLockError::Poisoned(e)) => return Err(GcLockTimeoutError::Poisoned(e)),
+            Err(GcTryLockError::WouldBlock) => {}
+        }
+
+        // (Our `GcGuard` has been dropped, so we aren't holding up the collector while we wait)
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(GcLockTimeoutError::TimedOut);
+        }
+        attempts += 1;
+        if attempts < LOCK_RETRY_SPINS {
+            thread::yield_now();
+        } else {
+            thread::sleep(cmp::min(LOCK_RETRY_SLEEP, deadline - now));
+        }
+    }
+}
+
+const LOCK_RETRY_SPINS: u32 = 16;
+const LOCK_RETRY_SLEEP: Duration = Duration::from_micros(100);
+
+// Whether locking poisoned data should give an error, according to the `PoisonPolicy`
+fn report_poison() -> bool {
+    COLLECTOR.poison_policy() == PoisonPolicy::Report
+}
+
 // This is special casing for Gc<Mutex<T>>
 // TODO: Rename `cell_ref`
 rental! {
     mod gc_mutex_internals {
         use std::sync::{Mutex, MutexGuard};
 
-        use crate::{Scan, GcGuard};
+        use crate::{Scan, GcGuard, OwnedGcGuard};
 
         /// Self referential wrapper around `MutexGuard` for ergonomics
         #[rental(deref_mut_suffix)]
@@ -139,16 +512,26 @@ rental! {
             gc_guard: GcGuard<'a, Mutex<T>>,
             cell_ref: MutexGuard<'gc_guard, T>
         }
+
+        /// Self referential wrapper around `MutexGuard`, holding its own `Gc`
+        #[rental(deref_mut_suffix)]
+        pub struct OwnedGcMutexGuardInt<T: Scan + 'static> {
+            gc_guard: OwnedGcGuard<Mutex<T>>,
+            cell_ref: MutexGuard<'gc_guard, T>
+        }
     }
 }
 
 /// This is like a `MutexGuard`, but taken directly from a `Gc`
 pub struct GcMutexGuard<'a, T: Scan + 'static> {
     internal_guard: gc_mutex_internals::GcMutexGuardInt<'a, T>,
+    /// kept so `GcCondvar` can lock the `Gc` again
+    gc: &'a Gc<sync::Mutex<T>>,
 }
 
 impl<'a, T: Scan + 'static> GcMutexGuard<'a, T> {
     pub(crate) fn lock(g: GcGuard<'a, sync::Mutex<T>>) -> Result<Self, GcPoisonError<Self>> {
+        let gc = GcGuard::gc_of(&g);
         let mut was_poisoned = false;
         let internal_guard = gc_mutex_internals::GcMutexGuardInt::new(g, |g| match g.lock() {
             Ok(v) => v,
@@ -158,16 +541,22 @@ impl<'a, T: Scan + 'static> GcMutexGuard<'a, T> {
             }
         });
 
-        let guard = Self { internal_guard };
+        let guard = Self { internal_guard, gc };
 
-        if was_poisoned {
+        if was_poisoned && report_poison() {
             Err(GcPoisonError { guard })
         } else {
             Ok(guard)
         }
     }
 
+    /// The `Gc` this guard was locked through
+    pub(crate) fn gc_of(this: &Self) -> &'a Gc<sync::Mutex<T>> {
+        this.gc
+    }
+
     pub(crate) fn try_lock(g: GcGuard<'a, sync::Mutex<T>>) -> Result<Self, GcTryLockError<Self>> {
+        let gc = GcGuard::gc_of(&g);
         let mut was_poisoned = false;
         let internal_guard =
             gc_mutex_internals::GcMutexGuardInt::try_new(g, |g| match g.try_lock() {
@@ -182,16 +571,38 @@ impl<'a, T: Scan + 'static> GcMutexGuard<'a, T> {
             })
             .map_err(|e| e.0)?;
 
-        let guard = GcMutexGuard { internal_guard };
+        let guard = GcMutexGuard { internal_guard, gc };
 
-        if was_poisoned {
+        if was_poisoned && report_poison() {
             Err(GcTryLockError::Poisoned(GcPoisonError { guard }))
         } else {
             Ok(guard)
         }
     }
+
+    /// Make a new guard for part of the locked data, like `MappedMutexGuard`
+    pub fn map<U: ?Sized, F>(this: Self, f: F) -> GcMappedGuardMut<'a, U>
+    where
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        GcMappedGuardMut::new(this, f)
+    }
+
+    /// Make a new guard for part of the locked data, or give back this guard if `f` returns `None`
+    ///
+    /// # Errors
+    /// Returns the original guard if `f` returns `None`
+    pub fn filter_map<U: ?Sized, F>(this: Self, f: F) -> Result<GcMappedGuardMut<'a, U>, Self>
+    where
+        F: FnOnce(&mut T) -> Option<&mut U>,
+    {
+        GcMappedGuardMut::try_new(this, f)
+    }
 }
 
+/// The locked data lives in the `Gc`, so it can't move
+unsafe impl<T: Scan + 'static> StableDeref for GcMutexGuard<'_, T> {}
+
 impl<T: Scan + 'static> Deref for GcMutexGuard<'_, T> {
     type Target = T;
 
@@ -214,11 +625,59 @@ impl<T: Scan + 'static + Debug> Debug for GcMutexGuard<'_, T> {
     }
 }
 
+/// Like a `GcMutexGuard`, but holding its own clone of the `Gc` (returned by `Gc::lock_owned`)
+pub struct OwnedGcMutexGuard<T: Scan + 'static> {
+    internal_guard: gc_mutex_internals::OwnedGcMutexGuardInt<T>,
+}
+
+impl<T: Scan + 'static> OwnedGcMutexGuard<T> {
+    pub(crate) fn lock(g: OwnedGcGuard<sync::Mutex<T>>) -> Result<Self, GcPoisonError<Self>> {
+        let mut was_poisoned = false;
+        let internal_guard = gc_mutex_internals::OwnedGcMutexGuardInt::new(g, |g| match g.lock() {
+            Ok(v) => v,
+            Err(e) => {
+                was_poisoned = true;
+                e.into_inner()
+            }
+        });
+
+        let guard = Self { internal_guard };
+
+        if was_poisoned && report_poison() {
+            Err(GcPoisonError { guard })
+        } else {
+            Ok(guard)
+        }
+    }
+}
+
+impl<T: Scan + 'static> Deref for OwnedGcMutexGuard<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.internal_guard.deref()
+    }
+}
+
+impl<T: Scan + 'static> DerefMut for OwnedGcMutexGuard<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.internal_guard.deref_mut()
+    }
+}
+
+impl<T: Scan + 'static + Debug> Debug for OwnedGcMutexGuard<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnedGcMutexGuard")
+            .field("guarding", self.deref())
+            .finish()
+    }
+}
+
 rental! {
     mod gc_rwlock_internals {
         use std::sync::{RwLock, MutexGuard, RwLockReadGuard, RwLockWriteGuard};
 
-        use crate::{Scan, GcGuard};
+        use crate::{Scan, GcGuard, OwnedGcGuard};
 
         /// Self referential wrapper around `RwLockReadGuard` for ergonomics
         #[rental(deref_suffix)]
@@ -233,6 +692,20 @@ rental! {
             gc_guard: GcGuard<'a, RwLock<T>>,
             cell_ref: RwLockWriteGuard<'gc_guard, T>
         }
+
+        /// Self referential wrapper around `RwLockReadGuard`, holding its own `Gc`
+        #[rental(deref_suffix)]
+        pub struct OwnedGcRwLockReadGuardInternal<T: Scan + 'static> {
+            gc_guard: OwnedGcGuard<RwLock<T>>,
+            cell_ref: RwLockReadGuard<'gc_guard, T>
+        }
+
+        /// Self referential wrapper around `RwLockWriteGuard`, holding its own `Gc`
+        #[rental(deref_mut_suffix)]
+        pub struct OwnedGcRwLockWriteGuardInternal<T: Scan + 'static> {
+            gc_guard: OwnedGcGuard<RwLock<T>>,
+            cell_ref: RwLockWriteGuard<'gc_guard, T>
+        }
     }
 }
 
@@ -255,7 +728,7 @@ impl<'a, T: Scan + 'static> GcRwLockReadGuard<'a, T> {
 
         let guard = Self { internal_guard };
 
-        if was_poisoned {
+        if was_poisoned && report_poison() {
             Err(GcPoisonError { guard })
         } else {
             Ok(guard)
@@ -280,14 +753,36 @@ impl<'a, T: Scan + 'static> GcRwLockReadGuard<'a, T> {
 
         let guard = Self { internal_guard };
 
-        if was_poisoned {
+        if was_poisoned && report_poison() {
             Err(GcTryLockError::Poisoned(GcPoisonError { guard }))
         } else {
             Ok(guard)
         }
     }
+
+    /// Make a new guard for part of the locked data, like `MappedRwLockReadGuard`
+    pub fn map<U: ?Sized, F>(this: Self, f: F) -> GcMappedGuard<'a, U>
+    where
+        F: FnOnce(&T) -> &U,
+    {
+        GcMappedGuard::new(this, f)
+    }
+
+    /// Make a new guard for part of the locked data, or give back this guard if `f` returns `None`
+    ///
+    /// # Errors
+    /// Returns the original guard if `f` returns `None`
+    pub fn filter_map<U: ?Sized, F>(this: Self, f: F) -> Result<GcMappedGuard<'a, U>, Self>
+    where
+        F: FnOnce(&T) -> Option<&U>,
+    {
+        GcMappedGuard::try_new(this, f)
+    }
 }
 
+/// The locked data lives in the `Gc`, so it can't move
+unsafe impl<T: Scan + 'static> StableDeref for GcRwLockReadGuard<'_, T> {}
+
 impl<T: Scan + 'static + Debug> Debug for GcRwLockReadGuard<'_, T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("GcRwLockReadGuard")
@@ -323,7 +818,7 @@ impl<'a, T: Scan + 'static> GcRwLockWriteGuard<'a, T> {
 
         let guard = Self { internal_guard };
 
-        if was_poisoned {
+        if was_poisoned && report_poison() {
             Err(GcPoisonError { guard })
         } else {
             Ok(guard)
@@ -347,14 +842,36 @@ impl<'a, T: Scan + 'static> GcRwLockWriteGuard<'a, T> {
 
         let guard = GcRwLockWriteGuard { internal_guard };
 
-        if was_poisoned {
+        if was_poisoned && report_poison() {
             Err(GcTryLockError::Poisoned(GcPoisonError { guard }))
         } else {
             Ok(guard)
         }
     }
+
+    /// Make a new guard for part of the locked data, like `MappedRwLockWriteGuard`
+    pub fn map<U: ?Sized, F>(this: Self, f: F) -> GcMappedGuardMut<'a, U>
+    where
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        GcMappedGuardMut::new(this, f)
+    }
+
+    /// Make a new guard for part of the locked data, or give back this guard if `f` returns `None`
+    ///
+    /// # Errors
+    /// Returns the original guard if `f` returns `None`
+    pub fn filter_map<U: ?Sized, F>(this: Self, f: F) -> Result<GcMappedGuardMut<'a, U>, Self>
+    where
+        F: FnOnce(&mut T) -> Option<&mut U>,
+    {
+        GcMappedGuardMut::try_new(this, f)
+    }
 }
 
+/// The locked data lives in the `Gc`, so it can't move
+unsafe impl<T: Scan + 'static> StableDeref for GcRwLockWriteGuard<'_, T> {}
+
 impl<T: Scan + 'static + Debug> Debug for GcRwLockWriteGuard<'_, T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("GcRwLockWriteGuard")
@@ -376,3 +893,505 @@ impl<'a, T: Scan + 'static> DerefMut for GcRwLockWriteGuard<'a, T> {
         self.internal_guard.deref_mut()
     }
 }
+
+/// Like a `GcRwLockReadGuard`, but holding its own clone of the `Gc` (returned by `Gc::read_owned`)
+pub struct OwnedGcRwLockReadGuard<T: Scan + 'static> {
+    internal_guard: gc_rwlock_internals::OwnedGcRwLockReadGuardInternal<T>,
+}
+
+impl<T: Scan + 'static> OwnedGcRwLockReadGuard<T> {
+    pub(crate) fn read(g: OwnedGcGuard<sync::RwLock<T>>) -> Result<Self, GcPoisonError<Self>> {
+        let mut was_poisoned = false;
+        let internal_guard =
+            gc_rwlock_internals::OwnedGcRwLockReadGuardInternal::new(g, |g| match g.read() {
+                Ok(v) => v,
+                Err(e) => {
+                    was_poisoned = true;
+                    e.into_inner()
+                }
+            });
+
+        let guard = Self { internal_guard };
+
+        if was_poisoned && report_poison() {
+            Err(GcPoisonError { guard })
+        } else {
+            Ok(guard)
+        }
+    }
+}
+
+impl<T: Scan + 'static + Debug> Debug for OwnedGcRwLockReadGuard<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnedGcRwLockReadGuard")
+            .field("guarding", self.deref())
+            .finish()
+    }
+}
+
+impl<T: Scan + 'static> Deref for OwnedGcRwLockReadGuard<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.internal_guard.deref()
+    }
+}
+
+/// Like a `GcRwLockWriteGuard`, but holding its own clone of the `Gc` (returned by `Gc::write_owned`)
+pub struct OwnedGcRwLockWriteGuard<T: Scan + 'static> {
+    internal_guard: gc_rwlock_internals::OwnedGcRwLockWriteGuardInternal<T>,
+}
+
+impl<T: Scan + 'static> OwnedGcRwLockWriteGuard<T> {
+    pub(crate) fn write(g: OwnedGcGuard<sync::RwLock<T>>) -> Result<Self, GcPoisonError<Self>> {
+        let mut was_poisoned = false;
+        let internal_guard =
+            gc_rwlock_internals::OwnedGcRwLockWriteGuardInternal::new(g, |g| match g.write() {
+                Ok(v) => v,
+                Err(e) => {
+                    was_poisoned = true;
+                    e.into_inner()
+                }
+            });
+
+        let guard = Self { internal_guard };
+
+        if was_poisoned && report_poison() {
+            Err(GcPoisonError { guard })
+        } else {
+            Ok(guard)
+        }
+    }
+}
+
+impl<T: Scan + 'static + Debug> Debug for OwnedGcRwLockWriteGuard<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnedGcRwLockWriteGuard")
+            .field("guarding", self.deref())
+            .finish()
+    }
+}
+
+impl<T: Scan + 'static> Deref for OwnedGcRwLockWriteGuard<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.internal_guard.deref()
+    }
+}
+
+impl<T: Scan + 'static> DerefMut for OwnedGcRwLockWriteGuard<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.internal_guard.deref_mut()
+    }
+}
+
+// This is special casing for Gc<parking_lot::Mutex<T>> and Gc<parking_lot::RwLock<T>>
+//
+// We don't need `rental` here: the lock lives in the `Gc` allocation, which can't move or be
+// freed while we hold the `GcGuard`. So the `parking_lot` guard can borrow it for `'a` directly,
+// which is also what lets an upgradable read be upgraded in place.
+
+/// This is like a `parking_lot::MutexGuard`, but taken directly from a `Gc`
+pub struct GcParkingLotMutexGuard<'a, T: Scan + 'static> {
+    // Declared first, so the lock is released before the warrant
+    lock_guard: MutexGuard<'a, T>,
+    _gc_guard: GcGuard<'a, parking_lot::Mutex<T>>,
+}
+
+impl<'a, T: Scan + 'static> GcParkingLotMutexGuard<'a, T> {
+    pub(crate) fn lock(g: GcGuard<'a, parking_lot::Mutex<T>>) -> Self {
+        // Safety: The lock guard is dropped before `g`
+        let mutex = unsafe { GcGuard::data_for_lifetime(&g) };
+        Self {
+            lock_guard: mutex.lock(),
+            _gc_guard: g,
+        }
+    }
+
+    pub(crate) fn try_lock(g: GcGuard<'a, parking_lot::Mutex<T>>) -> Option<Self> {
+        // Safety: The lock guard is dropped before `g`
+        let mutex = unsafe { GcGuard::data_for_lifetime(&g) };
+        Some(Self {
+            lock_guard: mutex.try_lock()?,
+            _gc_guard: g,
+        })
+    }
+
+    /// Make a new guard for part of the locked data, like `MappedMutexGuard`
+    pub fn map<U: ?Sized, F>(this: Self, f: F) -> GcMappedGuardMut<'a, U>
+    where
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        GcMappedGuardMut::new(this, f)
+    }
+
+    /// Make a new guard for part of the locked data, or give back this guard if `f` returns `None`
+    ///
+    /// # Errors
+    /// Returns the original guard if `f` returns `None`
+    pub fn filter_map<U: ?Sized, F>(this: Self, f: F) -> Result<GcMappedGuardMut<'a, U>, Self>
+    where
+        F: FnOnce(&mut T) -> Option<&mut U>,
+    {
+        GcMappedGuardMut::try_new(this, f)
+    }
+}
+
+/// The locked data lives in the `Gc`, so it can't move
+unsafe impl<T: Scan + 'static> StableDeref for GcParkingLotMutexGuard<'_, T> {}
+
+impl<T: Scan + 'static> Deref for GcParkingLotMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.lock_guard.deref()
+    }
+}
+
+impl<T: Scan + 'static> DerefMut for GcParkingLotMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.lock_guard.deref_mut()
+    }
+}
+
+impl<T: Scan + 'static + Debug> Debug for GcParkingLotMutexGuard<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcParkingLotMutexGuard")
+            .field("guarding", self.deref())
+            .finish()
+    }
+}
+
+/// This is like a `parking_lot::RwLockReadGuard`, but taken directly from a `Gc`
+pub struct GcParkingLotRwLockReadGuard<'a, T: Scan + 'static> {
+    // Declared first, so the lock is released before the warrant
+    lock_guard: RwLockReadGuard<'a, T>,
+    _gc_guard: GcGuard<'a, parking_lot::RwLock<T>>,
+}
+
+impl<'a, T: Scan + 'static> GcParkingLotRwLockReadGuard<'a, T> {
+    pub(crate) fn read(g: GcGuard<'a, parking_lot::RwLock<T>>) -> Self {
+        // Safety: The lock guard is dropped before `g`
+        let lock = unsafe { GcGuard::data_for_lifetime(&g) };
+        Self {
+            lock_guard: lock.read(),
+            _gc_guard: g,
+        }
+    }
+
+    pub(crate) fn try_read(g: GcGuard<'a, parking_lot::RwLock<T>>) -> Option<Self> {
+        // Safety: The lock guard is dropped before `g`
+        let lock = unsafe { GcGuard::data_for_lifetime(&g) };
+        Some(Self {
+            lock_guard: lock.try_read()?,
+            _gc_guard: g,
+        })
+    }
+
+    /// Make a new guard for part of the locked data, like `MappedRwLockReadGuard`
+    pub fn map<U: ?Sized, F>(this: Self, f: F) -> GcMappedGuard<'a, U>
+    where
+        F: FnOnce(&T) -> &U,
+    {
+        GcMappedGuard::new(this, f)
+    }
+
+    /// Make a new guard for part of the locked data, or give back this guard if `f` returns `None`
+    ///
+    /// # Errors
+    /// Returns the original guard if `f` returns `None`
+    pub fn filter_map<U: ?Sized, F>(this: Self, f: F) -> Result<GcMappedGuard<'a, U>, Self>
+    where
+        F: FnOnce(&T) -> Option<&U>,
+    {
+        GcMappedGuard::try_new(this, f)
+    }
+}
+
+/// The locked data lives in the `Gc`, so it can't move
+unsafe impl<T: Scan + 'static> StableDeref for GcParkingLotRwLockReadGuard<'_, T> {}
+
+impl<T: Scan + 'static> Deref for GcParkingLotRwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.lock_guard.deref()
+    }
+}
+
+impl<T: Scan + 'static + Debug> Debug for GcParkingLotRwLockReadGuard<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcParkingLotRwLockReadGuard")
+            .field("guarding", self.deref())
+            .finish()
+    }
+}
+
+/// This is like a `parking_lot::RwLockWriteGuard`, but taken directly from a `Gc`
+pub struct GcParkingLotRwLockWriteGuard<'a, T: Scan + 'static> {
+    // Declared first, so the lock is released before the warrant
+    lock_guard: RwLockWriteGuard<'a, T>,
+    gc_guard: GcGuard<'a, parking_lot::RwLock<T>>,
+}
+
+impl<'a, T: Scan + 'static> GcParkingLotRwLockWriteGuard<'a, T> {
+    pub(crate) fn write(g: GcGuard<'a, parking_lot::RwLock<T>>) -> Self {
+        // Safety: The lock guard is dropped before `g`
+        let lock = unsafe { GcGuard::data_for_lifetime(&g) };
+        Self {
+            lock_guard: lock.write(),
+            gc_guard: g,
+        }
+    }
+
+    pub(crate) fn try_write(g: GcGuard<'a, parking_lot::RwLock<T>>) -> Option<Self> {
+        // Safety: The lock guard is dropped before `g`
+        let lock = unsafe { GcGuard::data_for_lifetime(&g) };
+        Some(Self {
+            lock_guard: lock.try_write()?,
+            gc_guard: g,
+        })
+    }
+
+    /// Atomically turn this into a read guard, without letting any other writers in between
+    #[must_use]
+    pub fn downgrade(this: Self) -> GcParkingLotRwLockReadGuard<'a, T> {
+        GcParkingLotRwLockReadGuard {
+            lock_guard: RwLockWriteGuard::downgrade(this.lock_guard),
+            _gc_guard: this.gc_guard,
+        }
+    }
+
+    /// Make a new guard for part of the locked data, like `MappedRwLockWriteGuard`
+    pub fn map<U: ?Sized, F>(this: Self, f: F) -> GcMappedGuardMut<'a, U>
+    where
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        GcMappedGuardMut::new(this, f)
+    }
+
+    /// Make a new guard for part of the locked data, or give back this guard if `f` returns `None`
+    ///
+    /// # Errors
+    /// Returns the original guard if `f` returns `None`
+    pub fn filter_map<U: ?Sized, F>(this: Self, f: F) -> Result<GcMappedGuardMut<'a, U>, Self>
+    where
+        F: FnOnce(&mut T) -> Option<&mut U>,
+    {
+        GcMappedGuardMut::try_new(this, f)
+    }
+}
+
+/// The locked data lives in the `Gc`, so it can't move
+unsafe impl<T: Scan + 'static> StableDeref for GcParkingLotRwLockWriteGuard<'_, T> {}
+
+impl<T: Scan + 'static> Deref for GcParkingLotRwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.lock_guard.deref()
+    }
+}
+
+impl<T: Scan + 'static> DerefMut for GcParkingLotRwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.lock_guard.deref_mut()
+    }
+}
+
+impl<T: Scan + 'static + Debug> Debug for GcParkingLotRwLockWriteGuard<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcParkingLotRwLockWriteGuard")
+            .field("guarding", self.deref())
+            .finish()
+    }
+}
+
+/// This is like a `parking_lot::RwLockUpgradableReadGuard`, but taken directly from a `Gc`
+///
+/// It can be upgraded to a `GcParkingLotRwLockWriteGuard` without unlocking. The warrant on the
+/// `Gc` data is held the whole time, so the collector never sees the data mid-upgrade.
+pub struct GcParkingLotRwLockUpgradableReadGuard<'a, T: Scan + 'static> {
+    // Declared first, so the lock is released before the warrant
+    lock_guard: RwLockUpgradableReadGuard<'a, T>,
+    gc_guard: GcGuard<'a, parking_lot::RwLock<T>>,
+}
+
+impl<'a, T: Scan + 'static> GcParkingLotRwLockUpgradableReadGuard<'a, T> {
+    pub(crate) fn upgradable_read(g: GcGuard<'a, parking_lot::RwLock<T>>) -> Self {
+        // Safety: The lock guard is dropped before `g`
+        let lock = unsafe { GcGuard::data_for_lifetime(&g) };
+        Self {
+            lock_guard: lock.upgradable_read(),
+            gc_guard: g,
+        }
+    }
+
+    pub(crate) fn try_upgradable_read(g: GcGuard<'a, parking_lot::RwLock<T>>) -> Option<Self> {
+        // Safety: The lock guard is dropped before `g`
+        let lock = unsafe { GcGuard::data_for_lifetime(&g) };
+        Some(Self {
+            lock_guard: lock.try_upgradable_read()?,
+            gc_guard: g,
+        })
+    }
+
+    /// Atomically upgrade this to a write guard, blocking until the other readers are gone
+    #[must_use]
+    pub fn upgrade(this: Self) -> GcParkingLotRwLockWriteGuard<'a, T> {
+        GcParkingLotRwLockWriteGuard {
+            lock_guard: RwLockUpgradableReadGuard::upgrade(this.lock_guard),
+            gc_guard: this.gc_guard,
+        }
+    }
+
+    /// Try to atomically upgrade this to a write guard, without blocking
+    ///
+    /// # Errors
+    /// Returns the original guard if there are other readers
+    pub fn try_upgrade(this: Self) -> Result<GcParkingLotRwLockWriteGuard<'a, T>, Self> {
+        let gc_guard = this.gc_guard;
+        match RwLockUpgradableReadGuard::try_upgrade(this.lock_guard) {
+            Ok(lock_guard) => Ok(GcParkingLotRwLockWriteGuard {
+                lock_guard,
+                gc_guard,
+            }),
+            Err(lock_guard) => Err(Self {
+                lock_guard,
+                gc_guard,
+            }),
+        }
+    }
+
+    /// Atomically turn this into a plain read guard
+    #[must_use]
+    pub fn downgrade(this: Self) -> GcParkingLotRwLockReadGuard<'a, T> {
+        GcParkingLotRwLockReadGuard {
+            lock_guard: RwLockUpgradableReadGuard::downgrade(this.lock_guard),
+            _gc_guard: this.gc_guard,
+        }
+    }
+}
+
+impl<T: Scan + 'static> Deref for GcParkingLotRwLockUpgradableReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.lock_guard.deref()
+    }
+}
+
+impl<T: Scan + 'static + Debug> Debug for GcParkingLotRwLockUpgradableReadGuard<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcParkingLotRwLockUpgradableReadGuard")
+            .field("guarding", self.deref())
+            .finish()
+    }
+}
+
+/// A future that resolves to a `GcGuard`, returned by `Gc::get_async`
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct GcGetFuture<'a, T: Scan + ?Sized> {
+    pub(crate) gc: &'a Gc<T>,
+}
+
+impl<'a, T: Scan + ?Sized> Future for GcGetFuture<'a, T> {
+    type Output = GcGuard<'a, T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let gc: &'a Gc<T> = self.gc;
+        gc.poll_get(cx)
+    }
+}
+
+impl<T: Scan + ?Sized> Debug for GcGetFuture<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcGetFuture").finish()
+    }
+}
+
+/// A future that locks a `Gc<GcAsyncMutex<T>>`, returned by `Gc::lock_async`
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct GcAsyncMutexLockFuture<'a, T: Scan> {
+    pub(crate) gc: &'a Gc<GcAsyncMutex<T>>,
+}
+
+impl<'a, T: Scan> Future for GcAsyncMutexLockFuture<'a, T> {
+    type Output = GcAsyncMutexGuard<'a, T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let gc: &'a Gc<GcAsyncMutex<T>> = self.gc;
+        let gc_guard = match gc.poll_get(cx) {
+            Poll::Ready(g) => g,
+            Poll::Pending => return Poll::Pending,
+        };
+
+        // If we have to wait, `gc_guard` is dropped, so the collector isn't held up by us
+        gc_guard.poll_acquire(cx).map(|()| GcAsyncMutexGuard {
+            gc_guard,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<T: Scan> Debug for GcAsyncMutexLockFuture<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcAsyncMutexLockFuture").finish()
+    }
+}
+
+/// A guard for a locked `Gc<GcAsyncMutex<T>>`. The lock is released when it's dropped
+///
+/// The guard hands out `&T`, so it can only be shared between threads if `T: Sync`:
+/// ```compile_fail
+/// use std::cell::RefCell;
+/// use shredder::wrappers::GcAsyncMutexGuard;
+///
+/// fn is_sync<S: Sync>() {}
+/// is_sync::<GcAsyncMutexGuard<'static, RefCell<u32>>>();
+/// ```
+pub struct GcAsyncMutexGuard<'a, T: Scan> {
+    gc_guard: GcGuard<'a, GcAsyncMutex<T>>,
+    // Opts out of the auto traits, which would make this `Sync` whenever `T: Send`
+    _marker: PhantomData<*const ()>,
+}
+
+// Safety: The lock doesn't care which thread releases it, so the guard can move like a `&mut T`
+unsafe impl<T: Scan + Send> Send for GcAsyncMutexGuard<'_, T> {}
+// Safety: Sharing the guard only gives out `&T`
+unsafe impl<T: Scan + Send + Sync> Sync for GcAsyncMutexGuard<'_, T> {}
+
+impl<T: Scan> Deref for GcAsyncMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // Safety: We hold the lock
+        unsafe { &*self.gc_guard.data_ptr() }
+    }
+}
+
+impl<T: Scan> DerefMut for GcAsyncMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: We hold the lock
+        unsafe { &mut *self.gc_guard.data_ptr() }
+    }
+}
+
+/// The locked data lives in the `Gc`, so it can't move
+unsafe impl<T: Scan> StableDeref for GcAsyncMutexGuard<'_, T> {}
+
+impl<T: Scan> Drop for GcAsyncMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        // Safety: We hold the lock, and this guard is going away
+        unsafe { self.gc_guard.release() };
+    }
+}
+
+impl<T: Scan + Debug> Debug for GcAsyncMutexGuard<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcAsyncMutexGuard")
+            .field("guarding", self.deref())
+            .finish()
+    }
+}
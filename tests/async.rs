@@ -0,0 +1,114 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+use once_cell::sync::Lazy;
+
+use shredder::*;
+
+static TEST_MUTEX: Lazy<parking_lot::Mutex<()>> = Lazy::new(|| parking_lot::Mutex::new(()));
+
+// A minimal executor: runs a future on this thread, parking while it's pending
+struct ThreadWaker {
+    thread: Thread,
+    wakes: AtomicUsize,
+}
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.wakes.fetch_add(1, Ordering::SeqCst);
+        self.thread.unpark();
+    }
+}
+
+fn new_waker() -> (Arc<ThreadWaker>, Waker) {
+    let thread_waker = Arc::new(ThreadWaker {
+        thread: thread::current(),
+        wakes: AtomicUsize::new(0),
+    });
+    let waker = Waker::from(thread_waker.clone());
+    (thread_waker, waker)
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let (_, waker) = new_waker();
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(v) = future.as_mut().poll(&mut cx) {
+            return v;
+        }
+        thread::park();
+    }
+}
+
+#[test]
+fn get_async_gc() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let data = Gc::new(vec![1, 2, 3]);
+        let sum: u32 = block_on(async { data.get_async().await.iter().sum() });
+        assert_eq!(sum, 6);
+
+        // Collections running concurrently don't stop us from eventually getting the data
+        let collector = thread::spawn(|| {
+            for _ in 0..100 {
+                collect();
+            }
+        });
+        for _ in 0..100 {
+            assert_eq!(block_on(data.get_async()).len(), 3);
+        }
+        collector.join().unwrap();
+    });
+}
+
+#[derive(Scan)]
+struct Counter {
+    count: u32,
+    other: Option<Gc<u32>>,
+}
+
+#[test]
+fn async_mutex_gc() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let counter = Gc::new(GcAsyncMutex::new(Counter {
+            count: 0,
+            other: None,
+        }));
+
+        let held = block_on(counter.lock_async());
+
+        // A second lock waits, and is woken when the first guard is dropped
+        let (thread_waker, waker) = new_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut waiting = counter.lock_async();
+        assert!(Pin::new(&mut waiting).poll(&mut cx).is_pending());
+        assert_eq!(thread_waker.wakes.load(Ordering::SeqCst), 0);
+
+        // The waiting future doesn't hold the data, so the collector can still run
+        collect();
+
+        drop(held);
+        assert_eq!(thread_waker.wakes.load(Ordering::SeqCst), 1);
+        match Pin::new(&mut waiting).poll(&mut cx) {
+            Poll::Ready(mut guard) => {
+                guard.count += 1;
+                guard.other = Some(Gc::new(7));
+            }
+            Poll::Pending => panic!("the lock should be free"),
+        }
+
+        // The data inside the mutex is scanned
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 2);
+        let guard = block_on(counter.lock_async());
+        assert_eq!(guard.count, 1);
+        assert_eq!(*guard.other.as_ref().unwrap().get(), 7);
+    });
+    assert_eq!(number_of_tracked_allocations(), 0);
+}
@@ -0,0 +1,123 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+
+use shredder::*;
+
+static TEST_MUTEX: Lazy<parking_lot::Mutex<()>> = Lazy::new(|| parking_lot::Mutex::new(()));
+
+static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Scan)]
+struct Counted {
+    v: u64,
+}
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        DROP_COUNT.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[derive(Scan)]
+struct AllocatesOnDrop {
+    v: u64,
+}
+
+impl Drop for AllocatesOnDrop {
+    fn drop(&mut self) {
+        // Allocating in a destructor must never help or block (or we'd deadlock)
+        drop(Gc::new(Counted { v: self.v }));
+    }
+}
+
+#[derive(Scan)]
+struct WaitsOnDrop {
+    #[shredder(skip)]
+    release: GcSafeWrapper<Mutex<Receiver<()>>>,
+}
+
+impl Drop for WaitsOnDrop {
+    fn drop(&mut self) {
+        self.release.lock().unwrap().recv().unwrap();
+    }
+}
+
+#[test]
+fn help_policy_runs_all_destructors() {
+    let _guard = TEST_MUTEX.lock();
+    set_destructor_backlog_policy(DestructorBacklogPolicy::Help { limit: 0 });
+    DROP_COUNT.store(0, Ordering::SeqCst);
+
+    run_with_gc_cleanup(|| {
+        for i in 0..1000 {
+            drop(Gc::new(Counted { v: i }));
+        }
+        collect();
+
+        // This allocation may run some of the pending destructors
+        let _keep = Gc::new(Counted { v: 0 });
+    });
+
+    assert_eq!(DROP_COUNT.load(Ordering::SeqCst), 1001);
+    assert_eq!(number_of_pending_destructors(), 0);
+    assert_eq!(number_of_tracked_allocations(), 0);
+    set_destructor_backlog_policy(DestructorBacklogPolicy::Unbounded);
+}
+
+#[test]
+fn destructors_that_allocate_ignore_backpressure() {
+    let _guard = TEST_MUTEX.lock();
+    set_destructor_backlog_policy(DestructorBacklogPolicy::Block { limit: 0 });
+    DROP_COUNT.store(0, Ordering::SeqCst);
+
+    run_with_gc_cleanup(|| {
+        for i in 0..100 {
+            drop(Gc::new(AllocatesOnDrop { v: i }));
+        }
+        collect();
+        synchronize_destructors();
+    });
+
+    assert_eq!(DROP_COUNT.load(Ordering::SeqCst), 100);
+    assert_eq!(number_of_tracked_allocations(), 0);
+    set_destructor_backlog_policy(DestructorBacklogPolicy::Unbounded);
+}
+
+// Without a drop thread, `Block` just helps
+#[test]
+fn block_policy_waits_for_backlog() {
+    let _guard = TEST_MUTEX.lock();
+    set_destructor_backlog_policy(DestructorBacklogPolicy::Block { limit: 0 });
+
+    run_with_gc_cleanup(|| {
+        let (release_sender, release_receiver) = channel();
+        drop(Gc::new(WaitsOnDrop {
+            release: GcSafeWrapper::new(Mutex::new(release_receiver)),
+        }));
+        collect();
+        // The drop thread is now stuck, so the backlog can't shrink
+        assert_eq!(number_of_pending_destructors(), 1);
+
+        let (done_sender, done_receiver) = channel();
+        let allocator = thread::spawn(move || {
+            drop(Gc::new(Counted { v: 1 }));
+            done_sender.send(()).unwrap();
+        });
+
+        assert!(done_receiver
+            .recv_timeout(Duration::from_millis(200))
+            .is_err());
+
+        release_sender.send(()).unwrap();
+        done_receiver.recv().unwrap();
+        allocator.join().unwrap();
+    });
+
+    assert_eq!(number_of_pending_destructors(), 0);
+    set_destructor_backlog_policy(DestructorBacklogPolicy::Unbounded);
+}
@@ -0,0 +1,183 @@
+use std::mem::size_of;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Barrier;
+use std::thread;
+
+use once_cell::sync::Lazy;
+
+use shredder::*;
+
+static TEST_MUTEX: Lazy<parking_lot::Mutex<()>> = Lazy::new(|| parking_lot::Mutex::new(()));
+
+#[derive(Scan)]
+struct Node {
+    next: parking_lot::Mutex<Option<Gc<Node>>>,
+}
+
+#[test]
+fn allocations_are_attributed_to_their_context() {
+    let _guard = TEST_MUTEX.lock();
+    let tenant_a = AllocationContext(100);
+    let tenant_b = AllocationContext(101);
+
+    run_with_gc_cleanup(|| {
+        let a = with_gc_context(tenant_a, || {
+            // A cycle, which only a collection can free
+            let first = Gc::new(Node {
+                next: parking_lot::Mutex::new(None),
+            });
+            let second = Gc::new(Node {
+                next: parking_lot::Mutex::new(Some(first.clone())),
+            });
+            *first.get().next.lock() = Some(second);
+
+            Gc::new(1_u64)
+        });
+        let b = with_gc_context(tenant_b, || Gc::new(2_u32));
+        // Boxed data counts what the `Box` points to as well
+        let boxed = with_gc_context(tenant_b, || Gc::from_box(Box::new(4_u64)));
+        // Outside of any context, nothing is recorded
+        let _untracked = Gc::new(3_u16);
+
+        assert_eq!(context_stats(tenant_a).objects, 3);
+        assert_eq!(context_stats(tenant_b).objects, 2);
+        assert_eq!(
+            context_stats(tenant_b).bytes,
+            size_of::<u32>() + size_of::<Box<u64>>() + size_of::<u64>()
+        );
+
+        // After a collection, only the live objects are counted
+        collect();
+        let stats = context_stats(tenant_a);
+        assert_eq!(stats.objects, 1);
+        assert_eq!(stats.bytes, size_of::<u64>());
+
+        drop(a);
+        drop(b);
+        drop(boxed);
+        collect();
+        synchronize_destructors();
+        assert_eq!(context_stats(tenant_a), ContextStats::default());
+        assert_eq!(context_stats(tenant_b).objects, 0);
+        assert!(all_context_stats()
+            .iter()
+            .any(|(context, _)| *context == tenant_b));
+    });
+}
+
+#[test]
+fn contexts_nest_and_are_restored() {
+    let _guard = TEST_MUTEX.lock();
+    let outer = AllocationContext(200);
+    let inner = AllocationContext(201);
+
+    run_with_gc_cleanup(|| {
+        let _kept = with_gc_context(outer, || {
+            let inner_data = with_gc_context(inner, || Gc::new(1_u8));
+            let outer_data = Gc::new(2_u8);
+
+            // A panic inside a context still restores the outer one
+            let res = catch_unwind(AssertUnwindSafe(|| {
+                with_gc_context(inner, || panic!("tenant script failed"))
+            }));
+            assert!(res.is_err());
+            let after_panic = Gc::new(3_u8);
+
+            (inner_data, outer_data, after_panic)
+        });
+
+        assert_eq!(context_stats(outer).objects, 2);
+        assert_eq!(context_stats(inner).objects, 1);
+    });
+}
+
+#[test]
+fn quotas_fail_or_flag_allocations() {
+    let _guard = TEST_MUTEX.lock();
+    let tenant = AllocationContext(300);
+    set_context_quota(tenant, Some(2 * size_of::<u64>()));
+
+    run_with_gc_cleanup(|| {
+        with_gc_context(tenant, || {
+            let a = Gc::try_new(1_u64).unwrap();
+            let _b = Gc::try_new(2_u64).unwrap();
+            assert_eq!(Gc::try_new(3_u64).unwrap_err(), GcError::QuotaExceeded);
+
+            // `new` still allocates, but the overage is flagged
+            let c = Gc::new(4_u64);
+            let stats = context_stats(tenant);
+            assert_eq!(stats.quota, Some(2 * size_of::<u64>()));
+            assert_eq!(stats.objects, 3);
+            assert_eq!(stats.over_quota_allocations, 1);
+
+            // Freeing memory makes room again
+            drop(a);
+            drop(c);
+            collect();
+            assert!(Gc::try_new(5_u64).is_ok());
+        });
+
+        // Outside the context, the quota doesn't apply
+        assert!(Gc::try_new(6_u64).is_ok());
+        set_context_quota(tenant, None);
+        assert_eq!(context_stats(tenant).quota, None);
+    });
+}
+
+#[test]
+fn racing_allocations_respect_quotas() {
+    let _guard = TEST_MUTEX.lock();
+    let tenant = AllocationContext(400);
+    set_context_quota(tenant, Some(10 * size_of::<u64>()));
+
+    run_with_gc_cleanup(|| {
+        let start = Barrier::new(8);
+        let allocated: Vec<Gc<u64>> = thread::scope(|s| {
+            let workers: Vec<_> = (0..8)
+                .map(|_| {
+                    s.spawn(|| {
+                        with_gc_context(tenant, || {
+                            start.wait();
+                            (0..10)
+                                .filter_map(|i| Gc::try_new(i).ok())
+                                .collect::<Vec<_>>()
+                        })
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        });
+
+        // However the threads interleave, only the quota's worth of allocations can succeed
+        assert_eq!(allocated.len(), 10);
+        let stats = context_stats(tenant);
+        assert_eq!(stats.objects, 10);
+        assert_eq!(stats.bytes, 10 * size_of::<u64>());
+        assert_eq!(stats.over_quota_allocations, 0);
+    });
+    set_context_quota(tenant, None);
+}
+
+#[test]
+fn quotas_count_the_same_bytes_as_stats() {
+    let _guard = TEST_MUTEX.lock();
+    let tenant = AllocationContext(500);
+    // Room for a boxed `u64` (the box and what it points to), plus one more `u64`
+    let quota = size_of::<Box<u64>>() + 2 * size_of::<u64>();
+    set_context_quota(tenant, Some(quota));
+
+    run_with_gc_cleanup(|| {
+        with_gc_context(tenant, || {
+            let _boxed = Gc::from_box(Box::new(1_u64));
+            let _fits = Gc::try_new(2_u64).unwrap();
+            assert_eq!(context_stats(tenant).bytes, quota);
+            assert_eq!(Gc::try_new(3_u64).unwrap_err(), GcError::QuotaExceeded);
+            assert_eq!(context_stats(tenant).bytes, quota);
+            assert_eq!(context_stats(tenant).over_quota_allocations, 0);
+        });
+    });
+    set_context_quota(tenant, None);
+}
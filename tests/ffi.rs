@@ -0,0 +1,239 @@
+#![cfg(feature = "ffi")]
+
+use std::env;
+use std::ffi::c_void;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use once_cell::sync::Lazy;
+
+use shredder::ffi::*;
+use shredder::*;
+
+static TEST_MUTEX: Lazy<parking_lot::Mutex<()>> = Lazy::new(|| parking_lot::Mutex::new(()));
+
+static DESTROYED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe extern "C" fn destroy_counter(data: *mut c_void) {
+    drop(Box::from_raw(data.cast::<u32>()));
+    DESTROYED.fetch_add(1, Ordering::SeqCst);
+}
+
+#[test]
+fn handles_are_roots() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| unsafe {
+        let handle = ShredderHandle::new(Gc::new(String::from("hello"))).into_raw();
+        let clone = shredder_handle_clone(handle);
+        assert_eq!(shredder_handle_id(handle), shredder_handle_id(clone));
+        assert!(shredder_handle_clone(ptr::null()).is_null());
+        assert!(shredder_foreign_data(handle).is_null());
+
+        shredder_handle_drop(handle);
+        shredder_collect();
+        assert_eq!(shredder_number_of_tracked_allocations(), 1);
+        assert_eq!(&*(*clone).downcast_ref::<String>().unwrap().get(), "hello");
+        assert!((*clone).downcast_ref::<u32>().is_none());
+
+        shredder_handle_drop(clone);
+        shredder_handle_drop(ptr::null_mut());
+        shredder_collect();
+        assert_eq!(shredder_number_of_tracked_allocations(), 0);
+        assert_eq!(shredder_number_of_active_handles(), 0);
+    });
+}
+
+#[test]
+fn foreign_objects() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| unsafe {
+        DESTROYED.store(0, Ordering::SeqCst);
+
+        let data = Box::into_raw(Box::new(1_u32)).cast::<c_void>();
+        let a = shredder_register_foreign(data, Some(destroy_counter));
+        let b =
+            shredder_register_foreign(Box::into_raw(Box::new(2_u32)).cast(), Some(destroy_counter));
+        let no_destructor = shredder_register_foreign(ptr::null_mut(), None);
+        let rust_object = ShredderHandle::new(Gc::new(3_u32)).into_raw();
+        assert_eq!(shredder_foreign_data(a), data);
+
+        // A cycle between the foreign objects, which also keeps a Rust object alive
+        assert!(shredder_foreign_add_reference(a, b));
+        assert!(shredder_foreign_add_reference(b, a));
+        assert!(shredder_foreign_add_reference(b, rust_object));
+        assert!(!shredder_foreign_add_reference(rust_object, a));
+        shredder_handle_drop(b);
+        shredder_handle_drop(rust_object);
+
+        shredder_collect();
+        shredder_synchronize_destructors();
+        assert_eq!(shredder_number_of_tracked_allocations(), 4);
+        assert_eq!(DESTROYED.load(Ordering::SeqCst), 0);
+
+        shredder_handle_drop(a);
+        shredder_handle_drop(no_destructor);
+        shredder_collect();
+        shredder_synchronize_destructors();
+        assert_eq!(shredder_number_of_tracked_allocations(), 0);
+        assert_eq!(DESTROYED.load(Ordering::SeqCst), 2);
+    });
+}
+
+#[test]
+fn header_declares_every_export() {
+    let source = include_str!("../src/ffi.rs");
+    let header = include_str!("../include/shredder.h");
+
+    let mut lines = source.lines();
+    let mut exports = 0;
+    while let Some(line) = lines.next() {
+        if line.trim() != "#[no_mangle]" {
+            continue;
+        }
+        let signature = lines.next().unwrap();
+        let name = signature
+            .split("fn ")
+            .nth(1)
+            .and_then(|rest| rest.split('(').next())
+            .unwrap();
+        assert!(
+            header.contains(&format!(" {}(", name)) || header.contains(&format!("*{}(", name)),
+            "include/shredder.h is missing `{}`",
+            name
+        );
+        exports += 1;
+    }
+    assert_eq!(exports, 10);
+}
+
+// The C spelling of each type used in an exported signature
+fn c_type(rust_type: &str) -> String {
+    let rust_type = rust_type.trim();
+    if let Some(pointee) = rust_type.strip_prefix("*mut ") {
+        return format!("{} *", c_type(pointee));
+    }
+    if let Some(pointee) = rust_type.strip_prefix("*const ") {
+        return format!("const {} *", c_type(pointee));
+    }
+    match rust_type {
+        "" | "()" | "c_void" => "void",
+        "bool" => "bool",
+        "u64" => "uint64_t",
+        "usize" => "size_t",
+        "ShredderHandle" => "ShredderHandle",
+        // (A nullable function pointer is just a function pointer in C)
+        "Option<ShredderDestroyFn>" => "ShredderDestroyFn",
+        _ => panic!("no C type for `{}`, add one to this test", rust_type),
+    }
+    .to_string()
+}
+
+// Declare `name` with the C spelling of `rust_type`
+fn c_declarator(rust_type: &str, name: &str) -> String {
+    let ty = c_type(rust_type);
+    if ty.ends_with('*') {
+        format!("{}{}", ty, name)
+    } else {
+        format!("{} {}", ty, name)
+    }
+}
+
+// The C parameter list for a Rust one, like `a: u64, b: bool`
+fn c_params(params: &str) -> String {
+    let params: Vec<String> = params
+        .split(',')
+        .filter(|param| !param.trim().is_empty())
+        .map(|param| {
+            let (name, ty) = param.split_once(':').unwrap();
+            c_declarator(ty, name.trim())
+        })
+        .collect();
+    if params.is_empty() {
+        "void".to_string()
+    } else {
+        params.join(", ")
+    }
+}
+
+// Split a Rust `fn` signature into its parameters and its return type
+fn split_signature(signature: &str) -> (&str, &str) {
+    let params = &signature[signature.find('(').unwrap() + 1..signature.rfind(')').unwrap()];
+    let ret = signature.rsplit(')').next().unwrap();
+    let ret = ret.split("->").nth(1).unwrap_or("");
+    (params, ret.trim_end_matches(['{', ';']))
+}
+
+// Redeclares every export (and the destructor type) from its Rust signature, after including the
+// header. If the header disagrees with the Rust side, the C compiler rejects the conflict.
+#[test]
+fn header_matches_exported_signatures() {
+    let source = include_str!("../src/ffi.rs");
+    let header = Path::new(env!("CARGO_MANIFEST_DIR")).join("include/shredder.h");
+
+    let mut check = format!("#include \"{}\"\n\n", header.display());
+    let destroy_fn = source
+        .lines()
+        .find(|line| line.starts_with("pub type ShredderDestroyFn"))
+        .unwrap();
+    let (params, ret) = split_signature(destroy_fn);
+    check.push_str(&format!(
+        "typedef {}(*ShredderDestroyFn)({});\n",
+        c_declarator(ret, ""),
+        c_params(params)
+    ));
+
+    let mut lines = source.lines();
+    while let Some(line) = lines.next() {
+        if line.trim() != "#[no_mangle]" {
+            continue;
+        }
+        let mut signature = String::new();
+        for line in lines.by_ref() {
+            signature.push_str(line.trim());
+            if signature.ends_with('{') {
+                break;
+            }
+        }
+        let name = signature
+            .split("fn ")
+            .nth(1)
+            .and_then(|rest| rest.split('(').next())
+            .unwrap();
+        let (params, ret) = split_signature(&signature);
+        check.push_str(&format!(
+            "{}({});\n",
+            c_declarator(ret, name),
+            c_params(params)
+        ));
+    }
+
+    let check_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("shredder_header_check.c");
+    fs::write(&check_path, &check).unwrap();
+
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let output = Command::new(&compiler)
+        .args(["-std=c11", "-Wall", "-Werror", "-pedantic", "-fsyntax-only"])
+        .arg(&check_path)
+        .output();
+    let output = match output {
+        Ok(output) => output,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            eprintln!(
+                "skipping the header check, since there's no C compiler (`{}`)",
+                compiler
+            );
+            return;
+        }
+        Err(e) => panic!("couldn't run `{}`: {}", compiler, e),
+    };
+    assert!(
+        output.status.success(),
+        "include/shredder.h doesn't match src/ffi.rs:\n{}\n{}",
+        check,
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
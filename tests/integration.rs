@@ -1,6 +1,7 @@
 use std::cell::RefCell;
 use std::mem::drop;
 use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{self, Arc, Mutex};
 
 use once_cell::sync::Lazy;
@@ -221,3 +222,808 @@ fn no_drop_functional() {
     assert_eq!(&*(tracker.lock().unwrap()), "none");
     assert_eq!(number_of_tracked_allocations(), 0);
 }
+
+trait Node: Scan {
+    fn edges(&self) -> &RefCell<Vec<Gc<dyn Node>>>;
+}
+
+#[derive(Scan)]
+struct Branch {
+    edges: RefCell<Vec<Gc<dyn Node>>>,
+}
+
+impl Node for Branch {
+    fn edges(&self) -> &RefCell<Vec<Gc<dyn Node>>> {
+        &self.edges
+    }
+}
+
+#[test]
+fn dyn_trait_cycle_gc() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let a: Gc<dyn Node> = Gc::from_box(Box::new(Branch {
+            edges: RefCell::new(Vec::new()),
+        }));
+        let b: Gc<dyn Node> = Gc::from_box(Box::new(Branch {
+            edges: RefCell::new(vec![a.clone()]),
+        }));
+        a.get().edges().borrow_mut().push(b.clone());
+        drop(b);
+
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 2);
+        let b = a.get().edges().borrow()[0].clone();
+        assert_eq!(b.get().edges().borrow().len(), 1);
+
+        drop(a);
+        drop(b);
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 0);
+    });
+}
+
+static SLICE_DROPS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Scan)]
+struct SliceElement {
+    children: Gc<[Gc<u32>]>,
+}
+
+impl Drop for SliceElement {
+    fn drop(&mut self) {
+        SLICE_DROPS.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn slice_and_str_gc() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let numbers: Gc<[u32]> = (0..5).collect();
+        assert_eq!(&*numbers.get(), &[0, 1, 2, 3, 4]);
+        let empty: Gc<[u32]> = Gc::from(Vec::new());
+        assert!(empty.get().is_empty());
+
+        let s: Gc<str> = Gc::from("hello");
+        assert_eq!(&*s.get(), "hello");
+        assert_eq!(s.get().len(), 5);
+        let owned: Gc<str> = Gc::from(String::from("world"));
+        assert_eq!(&*owned.clone().get(), "world");
+
+        assert_eq!(number_of_tracked_allocations(), 4);
+    });
+    assert_eq!(number_of_tracked_allocations(), 0);
+}
+
+#[test]
+fn slice_elements_are_scanned_and_dropped() {
+    let _guard = TEST_MUTEX.lock();
+    SLICE_DROPS.store(0, Ordering::SeqCst);
+    run_with_gc_cleanup(|| {
+        let leaf = Gc::new(7);
+        let children: Gc<[Gc<u32>]> = Gc::from(&[leaf.clone(), leaf.clone()][..]);
+        let elements: Gc<[SliceElement]> = (0..3)
+            .map(|_| SliceElement {
+                children: children.clone(),
+            })
+            .collect();
+        drop(leaf);
+        drop(children);
+
+        // Everything is still reachable through `elements`
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 3);
+        assert_eq!(*elements.get()[2].children.get()[1].get(), 7);
+
+        drop(elements);
+        collect();
+        synchronize_destructors();
+        assert_eq!(SLICE_DROPS.load(Ordering::SeqCst), 3);
+    });
+    assert_eq!(number_of_tracked_allocations(), 0);
+}
+
+#[derive(Scan)]
+struct Parent {
+    children: Vec<Gc<Child>>,
+}
+
+#[derive(Scan)]
+struct Child {
+    parent: Gc<Parent>,
+    id: u32,
+}
+
+#[test]
+fn new_cyclic_gc() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let parent = Gc::new_cyclic(|me| {
+            assert!(!me.is_live());
+            assert_eq!(me.checked_get().err(), Some(GcError::Uninitialized));
+
+            let children = (0..3)
+                .map(|id| {
+                    Gc::new(Child {
+                        parent: me.clone(),
+                        id,
+                    })
+                })
+                .collect();
+
+            // The parent isn't tracked until it's constructed, but it still keeps its children alive
+            collect();
+            assert_eq!(number_of_tracked_allocations(), 3);
+
+            Parent { children }
+        });
+        assert_eq!(number_of_tracked_allocations(), 4);
+        assert!(parent.is_live());
+
+        collect();
+        let ids: Vec<u32> = parent.get().children.iter().map(|c| c.get().id).collect();
+        assert_eq!(ids, vec![0, 1, 2]);
+        let child = parent.get().children[2].clone();
+        assert_eq!(child.get().parent.get().children.len(), 3);
+
+        drop(parent);
+        drop(child);
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 0);
+    });
+}
+
+static ZERO_SIZED_DROPS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Scan)]
+struct ZeroSized;
+
+impl Drop for ZeroSized {
+    fn drop(&mut self) {
+        ZERO_SIZED_DROPS.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn new_cyclic_zero_sized() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        ZERO_SIZED_DROPS.store(0, Ordering::SeqCst);
+
+        let data = Gc::new_cyclic(|me| {
+            assert!(!me.is_live());
+            ZeroSized
+        });
+        let _ = data.get();
+        assert_eq!(number_of_tracked_allocations(), 1);
+
+        drop(data);
+        collect();
+        synchronize_destructors();
+        assert_eq!(ZERO_SIZED_DROPS.load(Ordering::SeqCst), 1);
+        assert_eq!(number_of_tracked_allocations(), 0);
+    });
+}
+
+#[test]
+fn new_cyclic_get_before_construction_panics() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let escaped = RefCell::new(None);
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Gc::new_cyclic(|me: &Gc<u32>| {
+                escaped.replace(Some(me.clone()));
+                *me.get()
+            })
+        }));
+        assert!(res.is_err());
+        assert_eq!(number_of_tracked_allocations(), 0);
+
+        // The clone that escaped is still unusable, but it's safe to keep around and collect
+        let escaped = escaped.into_inner().unwrap();
+        collect();
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| *escaped.get()));
+        assert!(res.is_err());
+        assert!(!escaped.is_live());
+        assert!(escaped.checked_get().is_err());
+    });
+    assert_eq!(number_of_active_handles(), 0);
+}
+
+#[test]
+fn mapped_guards() {
+    use shredder::wrappers::{GcMappedGuard, GcRef, GcRefMut};
+
+    #[derive(Debug, Scan)]
+    struct Pair {
+        left: Vec<u32>,
+        right: Option<String>,
+    }
+
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let cell = Gc::new(RefCell::new(Pair {
+            left: vec![1, 2],
+            right: None,
+        }));
+
+        let mut left = GcRefMut::map(cell.borrow_mut(), |p| &mut p.left);
+        left.push(3);
+        drop(left);
+
+        let right = GcRef::filter_map(cell.borrow(), |p| p.right.as_deref());
+        let pair = right.err().unwrap();
+        let left = GcRef::map(pair, |p| &p.left[..]);
+        let last = GcMappedGuard::map(left, |l| &l[2]);
+        // The original borrow is still held, and the data is still protected from collection
+        assert!(cell.try_borrow_mut().is_err());
+        collect();
+        assert_eq!(*last, 3);
+        drop(last);
+
+        let mutex = Gc::new(Mutex::new(Pair {
+            left: Vec::new(),
+            right: Some(String::from("hi")),
+        }));
+        let mut right =
+            wrappers::GcMutexGuard::filter_map(mutex.lock().unwrap(), |p| p.right.as_mut())
+                .ok()
+                .unwrap();
+        right.push('!');
+        drop(right);
+        assert_eq!(mutex.lock().unwrap().right.as_deref(), Some("hi!"));
+
+        let rwlock = Gc::new(sync::RwLock::new(vec![4, 5]));
+        let first = wrappers::GcRwLockReadGuard::map(rwlock.read().unwrap(), |v| &v[0]);
+        assert_eq!(*first, 4);
+        assert!(rwlock.try_write().is_err());
+    });
+}
+
+#[test]
+fn owned_guards() {
+    use shredder::wrappers::{OwnedGcMutexGuard, OwnedGcRef};
+
+    struct Holder {
+        guard: OwnedGcRef<Vec<u32>>,
+    }
+
+    fn make_holder() -> Holder {
+        // The `Gc` here is a temporary, but the guard keeps it alive
+        let gc = Gc::new(RefCell::new(vec![1, 2, 3]));
+        Holder {
+            guard: gc.borrow_owned(),
+        }
+    }
+
+    fn lock_all(gcs: &[Gc<Mutex<u32>>]) -> Vec<OwnedGcMutexGuard<u32>> {
+        gcs.iter().map(|gc| gc.lock_owned().unwrap()).collect()
+    }
+
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let holder = make_holder();
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 1);
+        assert_eq!(*holder.guard, vec![1, 2, 3]);
+        drop(holder);
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 0);
+
+        let gcs = vec![Gc::new(Mutex::new(1)), Gc::new(Mutex::new(2))];
+        for mut guard in lock_all(&gcs) {
+            *guard += 10;
+        }
+        assert_eq!(*gcs[1].lock().unwrap(), 12);
+
+        let rwlock = Gc::new(sync::RwLock::new(String::from("a")));
+        rwlock.write_owned().unwrap().push('b');
+        let read = rwlock.read_owned().unwrap();
+        drop(rwlock);
+        assert_eq!(&*read, "ab");
+
+        let plain = Gc::new(5).get_owned();
+        assert_eq!(*plain, 5);
+    });
+}
+
+#[test]
+fn condvar_producer_consumer() {
+    use std::thread;
+    use std::time::Duration;
+
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let queue: Gc<Mutex<Vec<Gc<u32>>>> = Gc::new(Mutex::new(Vec::new()));
+        let condvar = Arc::new(GcCondvar::new());
+
+        let consumer = {
+            let queue = queue.clone();
+            let condvar = condvar.clone();
+            thread::spawn(move || {
+                let mut total = 0;
+                for _ in 0..10 {
+                    let mut guard = condvar
+                        .wait_while(queue.lock().unwrap(), |q| q.is_empty())
+                        .unwrap();
+                    let item = guard.remove(0);
+                    total += *item.get();
+                }
+                total
+            })
+        };
+
+        for i in 0..10 {
+            queue.lock().unwrap().push(Gc::new(i));
+            condvar.notify_all();
+            // The consumer doesn't hold the data while it waits, so collection can scan the queue
+            collect();
+        }
+        assert_eq!(consumer.join().unwrap(), 45);
+
+        let (guard, res) = condvar
+            .wait_timeout(queue.lock().unwrap(), Duration::from_millis(10))
+            .unwrap();
+        assert!(res.timed_out());
+        assert!(guard.is_empty());
+    });
+}
+
+static SLOW_SCANS: AtomicUsize = AtomicUsize::new(0);
+static SCANS_STARTED: AtomicUsize = AtomicUsize::new(0);
+
+// Takes a while to scan (if `SLOW_SCANS` is set), so we can observe a collection in progress
+#[derive(Debug)]
+struct SlowScan;
+
+unsafe impl Scan for SlowScan {
+    fn scan(&self, _: &mut Scanner<'_>) {
+        if SLOW_SCANS.load(Ordering::SeqCst) != 0 {
+            SCANS_STARTED.fetch_add(1, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(200));
+        }
+    }
+}
+unsafe impl GcSafe for SlowScan {}
+
+#[test]
+fn timed_access() {
+    use std::thread;
+    use std::time::Duration;
+
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let data = Gc::new(Mutex::new(SlowScan));
+        assert!(data.get_timeout(Duration::ZERO).is_ok());
+        assert!(data.get_timeout(Duration::from_millis(10)).is_ok());
+
+        // A lock held by user code
+        let held = data.lock().unwrap();
+        match data.lock_timeout(Duration::from_millis(10)) {
+            Err(wrappers::GcLockTimeoutError::TimedOut) => {}
+            _ => panic!("the lock should have timed out"),
+        }
+        drop(held);
+
+        // The collector scanning the data
+        SCANS_STARTED.store(0, Ordering::SeqCst);
+        SLOW_SCANS.store(1, Ordering::SeqCst);
+        let collector = thread::spawn(collect);
+        while SCANS_STARTED.load(Ordering::SeqCst) == 0 {
+            thread::yield_now();
+        }
+        assert_eq!(
+            data.get_timeout(Duration::ZERO).err(),
+            Some(GcError::Timeout)
+        );
+        assert_eq!(
+            data.get_timeout(Duration::from_millis(10)).err(),
+            Some(GcError::Timeout)
+        );
+        match data.lock_timeout(Duration::from_millis(10)) {
+            Err(wrappers::GcLockTimeoutError::Gc(GcError::Timeout)) => {}
+            _ => panic!("the collector should have held up the lock"),
+        }
+        SLOW_SCANS.store(0, Ordering::SeqCst);
+        collector.join().unwrap();
+
+        assert!(data.lock_timeout(Duration::from_millis(10)).is_ok());
+
+        let rwlock = Gc::new(sync::RwLock::new(1));
+        let read = rwlock.read_timeout(Duration::from_millis(10)).unwrap();
+        assert!(rwlock.read_timeout(Duration::from_millis(10)).is_ok());
+        match rwlock.write_timeout(Duration::from_millis(10)) {
+            Err(wrappers::GcLockTimeoutError::TimedOut) => {}
+            _ => panic!("the write lock should have timed out"),
+        }
+        drop(read);
+        *rwlock.write_timeout(Duration::from_millis(10)).unwrap() += 1;
+    });
+}
+
+#[derive(Debug, Scan)]
+struct ParkingLotNode {
+    next: Option<Gc<parking_lot::Mutex<ParkingLotNode>>>,
+}
+
+#[test]
+fn parking_lot_locks() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        // Cycles through `parking_lot` locks are scanned and collected
+        let a: GParkingLotMutex<ParkingLotNode> =
+            Gc::new(parking_lot::Mutex::new(ParkingLotNode { next: None }));
+        let b = Gc::new(parking_lot::Mutex::new(ParkingLotNode {
+            next: Some(a.clone()),
+        }));
+        a.lock().next = Some(b.clone());
+        assert!(a.try_lock().is_some());
+        let held = a.lock();
+        assert!(a.try_lock().is_none());
+        drop(held);
+        drop(a);
+        drop(b);
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 0);
+
+        let rwlock: GParkingLotRwLock<u32> = Gc::new(parking_lot::RwLock::new(1));
+        let read = rwlock.read();
+        let upgradable = rwlock.upgradable_read();
+        assert!(rwlock.try_upgradable_read().is_none());
+        let upgradable =
+            match wrappers::GcParkingLotRwLockUpgradableReadGuard::try_upgrade(upgradable) {
+                Err(g) => g,
+                Ok(_) => panic!("the upgrade should have been blocked by the reader"),
+            };
+        drop(read);
+
+        // The warrant is held from the upgradable read through the write, so collecting in
+        // between leaves the data alone
+        collect();
+        let mut write = wrappers::GcParkingLotRwLockUpgradableReadGuard::upgrade(upgradable);
+        collect();
+        *write += 1;
+        let read = wrappers::GcParkingLotRwLockWriteGuard::downgrade(write);
+        assert_eq!(*read, 2);
+        assert!(rwlock.try_write().is_none());
+        drop(read);
+        assert_eq!(number_of_tracked_allocations(), 1);
+
+        *rwlock.write() += 1;
+        assert_eq!(*rwlock.try_read().unwrap(), 3);
+    });
+}
+
+#[test]
+fn parking_lot_collect_while_locked() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let b = Gc::new(parking_lot::Mutex::new(ParkingLotNode { next: None }));
+        let a = Gc::new(parking_lot::Mutex::new(ParkingLotNode { next: Some(b) }));
+
+        // `b` is only reachable through the locked `a`, which the collector can't look inside
+        let held = a.lock();
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 2);
+        assert!(held.next.as_ref().unwrap().lock().next.is_none());
+        drop(held);
+
+        let rwlock = Gc::new(parking_lot::RwLock::new(vec![Gc::new(1)]));
+        let write = rwlock.write();
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 4);
+        assert_eq!(*write[0].get(), 1);
+        drop(write);
+
+        drop(a);
+        drop(rwlock);
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 0);
+    });
+}
+
+#[test]
+// `ByIdentity` keys don't hash the (mutable) data
+#[allow(clippy::mutable_key_type)]
+fn identity_keys() {
+    use std::collections::{BTreeSet, HashMap, HashSet};
+
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let nodes: Vec<_> = (0..4)
+            .map(|i| {
+                Gc::new(RefCell::new(DirectedGraphNode {
+                    label: format!("{}", i),
+                    edges: Vec::new(),
+                }))
+            })
+            .collect();
+        // A cycle through every node, plus a self loop. (Comparing these by value would never end)
+        for (i, node) in nodes.iter().enumerate() {
+            let next = nodes[(i + 1) % nodes.len()].clone();
+            node.borrow_mut().edges.push(next);
+        }
+        nodes[0].borrow_mut().edges.push(nodes[0].clone());
+
+        assert!(Gc::ptr_eq(&nodes[0], &nodes[0].clone()));
+        assert!(!Gc::ptr_eq(&nodes[0], &nodes[1]));
+        assert_eq!(nodes[0].id(), nodes[0].clone().id());
+        assert_ne!(nodes[0].id(), nodes[1].id());
+
+        let mut visited = HashSet::new();
+        let mut stack = vec![nodes[0].clone()];
+        while let Some(node) = stack.pop() {
+            if visited.insert(ByIdentity(node.clone())) {
+                stack.extend(node.borrow().edges.iter().cloned());
+            }
+        }
+        assert_eq!(visited.len(), 4);
+
+        let labels: HashMap<_, _> = nodes
+            .iter()
+            .map(|n| (ByIdentity(n.clone()), n.borrow().label.clone()))
+            .collect();
+        assert_eq!(labels[&ByIdentity(nodes[2].clone())], "2");
+
+        // Ordered by id, which follows allocation order
+        let ordered: BTreeSet<_> = nodes.iter().rev().cloned().map(ByIdentity).collect();
+        let ids: Vec<_> = ordered.iter().map(|n| n.id()).collect();
+        let expected: Vec<_> = nodes.iter().map(Gc::id).collect();
+        assert_eq!(ids, expected);
+
+        // Ids aren't reused once data is collected
+        let old_ids: HashSet<_> = nodes.iter().map(Gc::id).collect();
+        drop((nodes, visited, stack, labels, ordered));
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 0);
+        assert!(!old_ids.contains(&Gc::new(0).id()));
+    });
+}
+
+trait Shape: Scan {
+    fn sides(&self) -> u32;
+}
+
+#[derive(Debug, Scan)]
+struct Square;
+
+impl Shape for Square {
+    fn sides(&self) -> u32 {
+        4
+    }
+}
+
+#[test]
+fn any_downcasting() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let number = Gc::new(7_u32);
+        assert_eq!(number.type_name(), std::any::type_name::<u32>());
+        assert!(Gc::new(String::new()).type_name().ends_with("String"));
+        assert_eq!(Gc::<[u8]>::from(vec![1, 2]).type_name(), "[u8]");
+        assert_eq!(Gc::<str>::from("hi").type_name(), "str");
+        assert!(Gc::new(Square).type_name().ends_with("Square"));
+        let shape: Gc<dyn Shape> = Gc::from_box(Box::new(Square));
+        assert_eq!(shape.get().sides(), 4);
+        assert!(shape.type_name().starts_with("dyn "));
+
+        let objects: Vec<Gc<dyn GcAny>> = vec![
+            number.clone().into_any(),
+            Gc::new(Square).into_any(),
+            Gc::from_box(Box::new(String::from("boxed")) as Box<dyn GcAny>),
+        ];
+        assert_eq!(objects[0].type_name(), std::any::type_name::<u32>());
+        assert!(objects[0].is::<u32>());
+        assert!(!objects[0].is::<u64>());
+        assert!(objects[1].is::<Square>());
+        // Boxed data reports the type behind the box, but can still be downcast
+        assert!(objects[2].type_name().starts_with("dyn "));
+        assert!(objects[2].is::<String>());
+
+        let mut objects = objects.into_iter();
+        let downcast_number = objects.next().unwrap().downcast::<u32>().unwrap();
+        assert!(Gc::ptr_eq(&downcast_number, &number));
+        let not_a_number = objects.next().unwrap().downcast::<u32>().unwrap_err();
+        assert!(not_a_number.downcast::<Square>().is_ok());
+        let boxed = objects.next().unwrap().downcast::<String>().unwrap();
+        assert_eq!(&*boxed.get(), "boxed");
+
+        drop((number, shape, downcast_number, boxed));
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 0);
+    });
+}
+
+#[test]
+fn any_downcasting_across_threads() {
+    use std::thread;
+
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let number = Gc::new(7_u32);
+        let objects: Vec<Gc<dyn GcAny + Send + Sync>> = vec![
+            number.clone().into_any_sync(),
+            Gc::from_box(Box::new(String::from("boxed")) as Box<dyn GcAny + Send + Sync>),
+        ];
+
+        let (downcast_number, boxed) = thread::spawn(move || {
+            let mut objects = objects.into_iter();
+            let number = objects.next().unwrap();
+            assert!(number.is::<u32>());
+            let not_a_string = number.downcast::<String>().unwrap_err();
+            let boxed = objects.next().unwrap().downcast::<String>().unwrap();
+            (not_a_string.downcast::<u32>().unwrap(), boxed)
+        })
+        .join()
+        .unwrap();
+
+        assert!(Gc::ptr_eq(&downcast_number, &number));
+        assert_eq!(&*boxed.get(), "boxed");
+    });
+}
+
+static DOWNCASTS_SEEN: Lazy<Mutex<Vec<(bool, bool)>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+#[derive(Scan)]
+struct Downcaster {
+    other: Mutex<Option<Gc<dyn GcAny + Send + Sync>>>,
+}
+
+impl Drop for Downcaster {
+    fn drop(&mut self) {
+        if let Some(other) = self.other.lock().unwrap().take() {
+            let is = other.is::<Downcaster>();
+            let downcast = other.downcast::<Downcaster>().is_ok();
+            DOWNCASTS_SEEN.lock().unwrap().push((is, downcast));
+        }
+    }
+}
+
+#[test]
+fn any_downcasting_deallocated_data() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        // Whichever half of the cycle is dropped last sees that the other is gone
+        DOWNCASTS_SEEN.lock().unwrap().clear();
+        let a = Gc::new(Downcaster {
+            other: Mutex::new(None),
+        });
+        let b = Gc::new(Downcaster {
+            other: Mutex::new(Some(a.clone().into_any_sync())),
+        });
+        *a.get().other.lock().unwrap() = Some(b.into_any_sync());
+        assert!(a.clone().into_any_sync().is::<Downcaster>());
+        drop(a);
+        collect();
+        synchronize_destructors();
+        let seen = DOWNCASTS_SEEN.lock().unwrap().clone();
+        assert_eq!(seen.len(), 2);
+        assert!(seen.contains(&(false, false)));
+        assert!(seen.iter().all(|(is, downcast)| is == downcast));
+
+        // Data that didn't record its type is checked without panicking too
+        let escaped = scope(|s| s.alloc(7_u32)).into_any();
+        assert!(!escaped.is::<u32>());
+        assert!(escaped.downcast::<u32>().is_err());
+        assert!(scope(|s| s.alloc(7_u32).into_any().is::<u32>()));
+    });
+}
+
+#[test]
+fn live_objects_by_type() {
+    #[derive(Scan)]
+    struct Tracked {
+        id: u32,
+    }
+
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let a = Gc::new(Tracked { id: 1 });
+        let b = Gc::new(Tracked { id: 2 });
+        let _other = Gc::new(3_u32);
+        let boxed = Gc::from_box(Box::new(Tracked { id: 4 }));
+
+        let found = live_objects::<Tracked>();
+        let mut ids: Vec<u32> = found.iter().map(|t| t.get().id).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, [1, 2, 4]);
+        assert!(found.iter().any(|t| Gc::ptr_eq(t, &a)));
+
+        // The handles we got back are roots, like any other `Gc`
+        drop(a);
+        drop(b);
+        drop(boxed);
+        collect();
+        assert_eq!(live_objects::<Tracked>().len(), 3);
+
+        drop(found);
+        collect();
+        assert!(live_objects::<Tracked>().is_empty());
+    });
+}
+
+#[test]
+fn live_objects_never_alias_get_mut() {
+    use std::sync::atomic::AtomicBool;
+    use std::thread;
+
+    #[derive(Scan)]
+    struct Mutated {
+        mutating: Mutex<u32>,
+    }
+
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let done = Arc::new(AtomicBool::new(false));
+        let observer = {
+            let done = done.clone();
+            thread::spawn(move || {
+                while !done.load(Ordering::SeqCst) {
+                    for found in live_objects::<Mutated>() {
+                        // Nobody can be mutating data we can see
+                        assert_eq!(*found.get().mutating.lock().unwrap(), 0);
+                    }
+                }
+            })
+        };
+
+        let mut mutated = 0;
+        for _ in 0..500 {
+            let mut data = Gc::new(Mutated {
+                mutating: Mutex::new(0),
+            });
+            // (This fails if the observer found our data first)
+            if let Some(mut guard) = data.get_mut() {
+                *guard.mutating.get_mut().unwrap() = 1;
+                thread::yield_now();
+                *guard.mutating.get_mut().unwrap() = 0;
+                mutated += 1;
+            }
+        }
+        done.store(true, Ordering::SeqCst);
+        observer.join().unwrap();
+        assert!(mutated > 0);
+    });
+}
+
+#[test]
+fn heap_bytes_tracks_payload_and_metadata() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let before = heap_bytes();
+
+        let data: Gc<[u64]> = Gc::from(vec![0_u64; 512]);
+        let clones: Vec<_> = (0..16).map(|_| data.clone()).collect();
+        let during = heap_bytes();
+        assert!(during.payload >= before.payload + 4096);
+        // Each extra `Gc` needs its own bookkeeping
+        assert!(during.metadata > before.metadata);
+
+        drop(clones);
+        drop(data);
+        collect();
+        synchronize_destructors();
+        assert_eq!(heap_bytes().payload, before.payload);
+    });
+}
+
+#[test]
+fn heap_bytes_counts_boxed_data() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let before = heap_bytes();
+
+        // The `Gc` holds the `Box`, and the `Box` holds the elements
+        let boxed: Gc<[u64]> = Gc::from_box(vec![0_u64; 512].into_boxed_slice());
+        assert_eq!(
+            heap_bytes().payload - before.payload,
+            std::mem::size_of::<Box<[u64]>>() + 4096
+        );
+
+        drop(boxed);
+        collect();
+        synchronize_destructors();
+        assert_eq!(heap_bytes().payload, before.payload);
+    });
+}
@@ -0,0 +1,150 @@
+use std::cell::{Cell, RefCell};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::thread;
+
+use shredder::*;
+
+// Every `LocalGc` test runs on its own thread, so each gets a fresh local heap
+
+thread_local! {
+    static DROP_COUNT: Cell<usize> = const { Cell::new(0) };
+}
+
+#[derive(Scan)]
+struct Node {
+    edges: RefCell<Vec<LocalGc<Node>>>,
+}
+
+impl Node {
+    fn new() -> Self {
+        Self {
+            edges: RefCell::new(Vec::new()),
+        }
+    }
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        DROP_COUNT.with(|count| count.set(DROP_COUNT.with(Cell::get) + 1));
+    }
+}
+
+#[test]
+fn local_cycles_are_collected() {
+    thread::spawn(|| {
+        let a = LocalGc::new(Node::new());
+        let b = LocalGc::new(Node::new());
+        a.edges.borrow_mut().push(b.clone());
+        b.edges.borrow_mut().push(a.clone());
+        drop(b);
+
+        collect_local();
+        assert_eq!(number_of_local_allocations(), 2);
+        assert_eq!(DROP_COUNT.with(Cell::get), 0);
+
+        drop(a);
+        collect_local();
+        assert_eq!(number_of_local_allocations(), 0);
+        assert_eq!(DROP_COUNT.with(Cell::get), 2);
+    })
+    .join()
+    .unwrap();
+}
+
+#[test]
+fn local_heaps_are_per_thread() {
+    thread::spawn(|| {
+        let _data = LocalGc::new(1_u32);
+        thread::spawn(|| {
+            assert_eq!(number_of_local_allocations(), 0);
+        })
+        .join()
+        .unwrap();
+        assert_eq!(number_of_local_allocations(), 1);
+    })
+    .join()
+    .unwrap();
+}
+
+#[derive(Scan)]
+struct Peeker {
+    other: RefCell<Option<LocalGc<Peeker>>>,
+}
+
+impl Drop for Peeker {
+    fn drop(&mut self) {
+        if let Some(other) = &*self.other.borrow() {
+            let _ = catch_unwind(AssertUnwindSafe(|| {
+                let _ = other.other.borrow();
+            }))
+            .expect_err("accessing collected data should panic");
+        }
+    }
+}
+
+#[test]
+fn collected_data_panics_on_access() {
+    thread::spawn(|| {
+        let a = LocalGc::new(Peeker {
+            other: RefCell::new(None),
+        });
+        let b = LocalGc::new(Peeker {
+            other: RefCell::new(Some(a.clone())),
+        });
+        *a.other.borrow_mut() = Some(b);
+        drop(a);
+
+        collect_local();
+        assert_eq!(number_of_local_allocations(), 0);
+    })
+    .join()
+    .unwrap();
+}
+
+thread_local! {
+    static ESCAPED: RefCell<Vec<LocalGc<Escaper>>> = const { RefCell::new(Vec::new()) };
+}
+
+#[derive(Scan)]
+struct Escaper {
+    other: RefCell<Option<LocalGc<Escaper>>>,
+}
+
+impl Drop for Escaper {
+    fn drop(&mut self) {
+        if let Some(other) = &*self.other.borrow() {
+            ESCAPED.with(|escaped| escaped.borrow_mut().push(other.clone()));
+        }
+    }
+}
+
+#[test]
+fn handles_escaping_destructors_stay_valid() {
+    thread::spawn(|| {
+        let a = LocalGc::new(Escaper {
+            other: RefCell::new(None),
+        });
+        let b = LocalGc::new(Escaper {
+            other: RefCell::new(Some(a.clone())),
+        });
+        *a.other.borrow_mut() = Some(b);
+        drop(a);
+
+        collect_local();
+        assert_eq!(number_of_local_allocations(), 0);
+
+        // The escaped handles still point at valid (if dead) memory, which they free when dropped
+        let escaped = ESCAPED.with(|escaped| escaped.take());
+        assert_eq!(escaped.len(), 2);
+        for handle in &escaped {
+            let cloned = handle.clone();
+            let res = catch_unwind(AssertUnwindSafe(|| {
+                let _ = cloned.other.borrow();
+            }));
+            assert!(res.is_err());
+        }
+        drop(escaped);
+    })
+    .join()
+    .unwrap();
+}
@@ -0,0 +1,117 @@
+use std::sync::{Mutex, RwLock};
+use std::thread;
+
+use once_cell::sync::Lazy;
+
+use shredder::*;
+
+static TEST_MUTEX: Lazy<parking_lot::Mutex<()>> = Lazy::new(|| parking_lot::Mutex::new(()));
+
+#[derive(Debug, Scan)]
+struct Node {
+    value: u32,
+    next: Option<Gc<Mutex<Node>>>,
+}
+
+fn poison_mutex<T: Scan + Send + 'static>(data: &Gc<Mutex<T>>) {
+    let data = data.clone();
+    let res = thread::spawn(move || {
+        let _guard = data.lock();
+        panic!("poisoning the mutex");
+    })
+    .join();
+    assert!(res.is_err());
+}
+
+fn poison_rwlock<T: Scan + Send + Sync + 'static>(data: &Gc<RwLock<T>>) {
+    let data = data.clone();
+    let res = thread::spawn(move || {
+        let _guard = data.write();
+        panic!("poisoning the rwlock");
+    })
+    .join();
+    assert!(res.is_err());
+}
+
+#[test]
+fn poisoned_mutex_is_scanned() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        // A cycle that's only reachable through the poisoned mutex
+        let a = Gc::new(Mutex::new(Node {
+            value: 1,
+            next: None,
+        }));
+        let b = Gc::new(Mutex::new(Node {
+            value: 2,
+            next: Some(a.clone()),
+        }));
+        a.lock().unwrap().next = Some(b.clone());
+        let root = Gc::new(Mutex::new(Some(a)));
+        drop(b);
+
+        poison_mutex(&root);
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 3);
+
+        let guard = root.lock().unwrap_err().into_inner();
+        let a = guard.as_ref().unwrap();
+        let b = a.lock().unwrap().next.clone().unwrap();
+        assert_eq!(b.lock().unwrap().value, 2);
+        drop(b);
+        drop(guard);
+
+        drop(root);
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 0);
+    });
+}
+
+#[test]
+fn poisoned_rwlock_is_scanned() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let root = Gc::new(RwLock::new(vec![Gc::new(7_u32)]));
+
+        poison_rwlock(&root);
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 2);
+        assert_eq!(*root.read().unwrap_err().into_inner()[0].get(), 7);
+
+        drop(root);
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 0);
+    });
+}
+
+#[test]
+fn poison_policy() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let mutex = Gc::new(Mutex::new(Gc::new(1_u32)));
+        let rwlock = Gc::new(RwLock::new(2_u32));
+        poison_mutex(&mutex);
+        poison_rwlock(&rwlock);
+
+        assert!(mutex.lock().is_err());
+        assert!(mutex.try_lock().is_err());
+        assert!(rwlock.read().is_err());
+        assert!(rwlock.write().is_err());
+
+        set_poison_policy(PoisonPolicy::Ignore);
+        assert_eq!(*mutex.lock().unwrap().get(), 1);
+        assert!(mutex.try_lock().is_ok());
+        assert!(mutex.lock_owned().is_ok());
+        assert_eq!(*rwlock.read().unwrap(), 2);
+        *rwlock.write().unwrap() += 1;
+        assert_eq!(*rwlock.try_read().unwrap(), 3);
+
+        // The policy only changes what locking returns, the data is still poisoned
+        set_poison_policy(PoisonPolicy::Report);
+        assert!(mutex.lock().is_err());
+        assert!(rwlock.try_write().is_err());
+
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 3);
+    });
+}
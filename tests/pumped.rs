@@ -0,0 +1,74 @@
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ThreadId};
+
+use once_cell::sync::Lazy;
+
+use shredder::*;
+
+static TEST_MUTEX: Lazy<parking_lot::Mutex<()>> = Lazy::new(|| parking_lot::Mutex::new(()));
+
+#[derive(Scan)]
+struct RecordsDropThread {
+    #[shredder(skip)]
+    dropped_on: GcSafeWrapper<Arc<Mutex<Vec<ThreadId>>>>,
+}
+
+impl Drop for RecordsDropThread {
+    fn drop(&mut self) {
+        self.dropped_on.lock().unwrap().push(thread::current().id());
+    }
+}
+
+#[test]
+fn pumped_destructors_run_on_caller() {
+    let _guard = TEST_MUTEX.lock();
+    let dropped_on = Arc::new(Mutex::new(Vec::new()));
+
+    set_destructor_mode(DestructorMode::CallerPumped);
+    for _ in 0..3 {
+        drop(Gc::new(RecordsDropThread {
+            dropped_on: GcSafeWrapper::new(dropped_on.clone()),
+        }));
+    }
+    collect();
+    collect();
+
+    // Nothing runs until we pump
+    assert!(dropped_on.lock().unwrap().is_empty());
+    assert_eq!(number_of_pending_destructors(), 3);
+
+    assert_eq!(run_pending_destructors(1), 1);
+    assert_eq!(number_of_pending_destructors(), 2);
+
+    // `synchronize_destructors` runs everything else on this thread
+    synchronize_destructors();
+    assert_eq!(number_of_pending_destructors(), 0);
+    assert_eq!(run_pending_destructors(10), 0);
+
+    let me = thread::current().id();
+    assert_eq!(*dropped_on.lock().unwrap(), vec![me, me, me]);
+
+    set_destructor_mode(DestructorMode::Background);
+    assert_eq!(number_of_tracked_allocations(), 0);
+}
+
+#[test]
+fn switching_to_background_hands_off_garbage() {
+    let _guard = TEST_MUTEX.lock();
+    let dropped_on = Arc::new(Mutex::new(Vec::new()));
+
+    set_destructor_mode(DestructorMode::CallerPumped);
+    drop(Gc::new(RecordsDropThread {
+        dropped_on: GcSafeWrapper::new(dropped_on.clone()),
+    }));
+    collect();
+    assert_eq!(number_of_pending_destructors(), 1);
+
+    set_destructor_mode(DestructorMode::Background);
+    synchronize_destructors();
+    assert_eq!(number_of_pending_destructors(), 0);
+
+    let dropped_on = dropped_on.lock().unwrap();
+    assert_eq!(dropped_on.len(), 1);
+    assert_ne!(dropped_on[0], thread::current().id());
+}
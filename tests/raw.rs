@@ -0,0 +1,141 @@
+use std::ffi::c_void;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use once_cell::sync::Lazy;
+
+use shredder::*;
+
+static TEST_MUTEX: Lazy<parking_lot::Mutex<()>> = Lazy::new(|| parking_lot::Mutex::new(()));
+
+#[derive(Scan)]
+struct Node {
+    value: u32,
+    next: parking_lot::Mutex<Option<Gc<Node>>>,
+}
+
+// Stands in for a C library holding onto our user data
+static HELD_BY_C: Lazy<parking_lot::Mutex<Vec<usize>>> =
+    Lazy::new(|| parking_lot::Mutex::new(Vec::new()));
+
+extern "C" fn register_callback(user_data: *mut c_void) {
+    HELD_BY_C.lock().push(user_data as usize);
+}
+
+extern "C" fn run_callback(user_data: *mut c_void) -> u32 {
+    let node = unsafe { Gc::from_raw(user_data as *const Gc<Node>) };
+    let value = node.get().value;
+    value
+}
+
+#[test]
+fn raw_pointers_stay_rooted() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        // A cycle that's only reachable through the raw pointer
+        let a = Gc::new(Node {
+            value: 1,
+            next: parking_lot::Mutex::new(None),
+        });
+        let b = Gc::new(Node {
+            value: 2,
+            next: parking_lot::Mutex::new(Some(a.clone())),
+        });
+        *a.get().next.lock() = Some(b);
+
+        register_callback(Gc::into_raw(a) as *mut c_void);
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 2);
+
+        let user_data = HELD_BY_C.lock().pop().unwrap();
+        assert_eq!(run_callback(user_data as *mut c_void), 1);
+
+        // The reclaimed `Gc` was dropped at the end of the callback, so the cycle is garbage now
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 0);
+    });
+}
+
+#[test]
+fn raw_pointers_from_clones() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let data = Gc::new(5_u32);
+        let raw_a = Gc::into_raw(data.clone());
+        let raw_b = Gc::into_raw(data.clone());
+        assert_ne!(raw_a, raw_b);
+
+        drop(data);
+        let a = unsafe { Gc::from_raw(raw_a) };
+        collect();
+        assert_eq!(*a.get(), 5);
+        drop(a);
+
+        // The other raw pointer still keeps the data alive
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 1);
+        let b = unsafe { Gc::from_raw(raw_b) };
+        assert_eq!(*b.get(), 5);
+        drop(b);
+
+        collect();
+        assert_eq!(number_of_tracked_allocations(), 0);
+    });
+}
+
+#[test]
+fn raw_pointers_of_unsized_data() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let raw = Gc::into_raw(Gc::<str>::from("hello"));
+        collect();
+        let s = unsafe { Gc::from_raw(raw) };
+        assert_eq!(&*s.get(), "hello");
+    });
+}
+
+#[test]
+fn double_reclaim_panics() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let raw = Gc::into_raw(Gc::new(3_u32));
+        drop(unsafe { Gc::from_raw(raw) });
+
+        let res = catch_unwind(AssertUnwindSafe(|| unsafe { Gc::from_raw(raw) }));
+        assert!(res.is_err());
+    });
+}
+
+#[derive(Scan)]
+struct Unit;
+
+#[test]
+fn raw_pointers_of_zero_sized_data() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        // These share an address with each other, but their raw pointers don't
+        let raw_a = Gc::into_raw(Gc::new(Unit));
+        let raw_b = Gc::into_raw(Gc::new(Unit));
+        assert_ne!(raw_a, raw_b);
+
+        let a = unsafe { Gc::from_raw(raw_a) };
+        let b = unsafe { Gc::from_raw(raw_b) };
+        assert!(!Gc::ptr_eq(&a, &b));
+    });
+    assert_eq!(number_of_active_handles(), 0);
+}
+
+#[test]
+fn raw_pointers_of_scoped_data() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        // The scope frees this data while C still holds the pointer, so its address can be reused
+        let raw = scope(|s| Gc::into_raw(s.alloc(1_u32)));
+        let other = Gc::into_raw(Gc::new(2_u32));
+
+        let reclaimed = unsafe { Gc::from_raw(raw) };
+        assert!(!reclaimed.is_live());
+        let other = unsafe { Gc::from_raw(other) };
+        assert_eq!(*other.get(), 2);
+    });
+    assert_eq!(number_of_active_handles(), 0);
+}
@@ -0,0 +1,272 @@
+use std::cell::{Cell, RefCell};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread::{self, ThreadId};
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+
+use shredder::*;
+
+static TEST_MUTEX: Lazy<parking_lot::Mutex<()>> = Lazy::new(|| parking_lot::Mutex::new(()));
+
+#[derive(Scan)]
+struct Counted<'a> {
+    drops: R<'a, AtomicUsize>,
+    next: RefCell<Option<Gc<Counted<'a>>>>,
+}
+
+impl<'a> Counted<'a> {
+    fn new(drops: &'a AtomicUsize) -> Self {
+        Self {
+            drops: R::new(drops),
+            next: RefCell::new(None),
+        }
+    }
+}
+
+impl Drop for Counted<'_> {
+    fn drop(&mut self) {
+        self.drops.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn scope_destroys_reachable_data() {
+    let _guard = TEST_MUTEX.lock();
+    let drops = AtomicUsize::new(0);
+
+    scope(|s| {
+        let a = s.alloc(Counted::new(&drops));
+        let b = s.alloc(Counted::new(&drops));
+        a.get().next.replace(Some(b.clone()));
+        b.get().next.replace(Some(a.clone()));
+
+        // Still rooted, so this can't collect anything
+        collect();
+        synchronize_destructors();
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+    });
+
+    assert_eq!(drops.load(Ordering::SeqCst), 2);
+    assert_eq!(number_of_tracked_allocations(), 0);
+}
+
+#[test]
+fn collected_scope_data_is_dropped_once() {
+    let _guard = TEST_MUTEX.lock();
+    let drops = AtomicUsize::new(0);
+
+    scope(|s| {
+        drop(s.alloc(Counted::new(&drops)));
+        collect();
+        synchronize_destructors();
+        // Scoped data is only dropped on the scope's thread
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        assert_eq!(drain_thread_local_drops(), 1);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+        // This one is collected, but its destructor hasn't run when the scope ends
+        drop(s.alloc(Counted::new(&drops)));
+        collect();
+        synchronize_destructors();
+    });
+
+    assert_eq!(drops.load(Ordering::SeqCst), 2);
+    synchronize_destructors();
+    assert_eq!(drops.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn escaped_gc_panics_on_access() {
+    let _guard = TEST_MUTEX.lock();
+    let drops = AtomicUsize::new(0);
+
+    let escaped = scope(|s| s.alloc(Counted::new(&drops)));
+    assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+    let res = catch_unwind(AssertUnwindSafe(|| {
+        let _ = escaped.get();
+    }));
+    assert!(res.is_err());
+
+    // The handle itself can still be dropped (and collected around) as usual
+    collect();
+    drop(escaped);
+    assert_eq!(number_of_active_handles(), 0);
+}
+
+#[test]
+fn escaped_gc_fallible_access() {
+    let _guard = TEST_MUTEX.lock();
+
+    let escaped = scope(|s| {
+        let data = s.alloc(RefCell::new(1));
+        assert!(data.is_live());
+        assert_eq!(*data.checked_borrow().unwrap(), 1);
+        data
+    });
+
+    assert!(!escaped.is_live());
+    assert_eq!(escaped.checked_get().err(), Some(GcError::Deallocated));
+    assert_eq!(
+        escaped.get_timeout(Duration::ZERO).err(),
+        Some(GcError::Deallocated)
+    );
+    assert_eq!(escaped.checked_borrow().err(), Some(GcError::Deallocated));
+    assert_eq!(
+        escaped.checked_borrow_mut().err(),
+        Some(GcError::Deallocated)
+    );
+
+    let escaped_mutex = scope(|s| s.alloc(std::sync::Mutex::new(1)));
+    match escaped_mutex.checked_lock() {
+        Err(wrappers::GcLockError::Gc(GcError::Deallocated)) => {}
+        _ => panic!("the mutex should have been deallocated"),
+    }
+
+    let escaped_rwlock = scope(|s| s.alloc(parking_lot::RwLock::new(1)));
+    assert_eq!(
+        escaped_rwlock.checked_read().err(),
+        Some(GcError::Deallocated)
+    );
+    assert_eq!(
+        escaped_rwlock.checked_write().err(),
+        Some(GcError::Deallocated)
+    );
+
+    drop(escaped);
+    drop(escaped_mutex);
+    drop(escaped_rwlock);
+    assert_eq!(number_of_active_handles(), 0);
+}
+
+#[derive(Scan)]
+struct Inspector<'a> {
+    seen: R<'a, RefCell<Vec<Result<(), GcError>>>>,
+    other: RefCell<Option<Gc<Inspector<'a>>>>,
+}
+
+impl Drop for Inspector<'_> {
+    fn drop(&mut self) {
+        if let Some(other) = self.other.borrow().as_ref() {
+            let res = other.checked_get().map(|_| ());
+            self.seen.borrow_mut().push(res);
+        }
+    }
+}
+
+#[test]
+fn destructor_sees_deallocated_data() {
+    let _guard = TEST_MUTEX.lock();
+    let seen = RefCell::new(Vec::new());
+
+    scope(|s| {
+        let a = s.alloc(Inspector {
+            seen: R::new(&seen),
+            other: RefCell::new(None),
+        });
+        let b = s.alloc(Inspector {
+            seen: R::new(&seen),
+            other: RefCell::new(Some(a.clone())),
+        });
+        a.get().other.replace(Some(b));
+    });
+
+    // Both were destroyed together, so neither destructor could access the other
+    assert_eq!(
+        *seen.borrow(),
+        vec![Err(GcError::Deallocated), Err(GcError::Deallocated)]
+    );
+}
+
+#[test]
+fn scope_cleans_up_on_panic() {
+    let _guard = TEST_MUTEX.lock();
+    let drops = AtomicUsize::new(0);
+
+    let res = catch_unwind(AssertUnwindSafe(|| {
+        scope(|s| {
+            let _data = s.alloc(Counted::new(&drops));
+            panic!("oh no");
+        })
+    }));
+
+    assert!(res.is_err());
+    assert_eq!(drops.load(Ordering::SeqCst), 1);
+}
+
+// Holds `!Sync` borrowed state, so it must never be dropped off the scope's thread
+#[derive(Scan)]
+struct ThreadBound<'a> {
+    drops: R<'a, Cell<usize>>,
+    dropped_on: R<'a, Cell<Option<ThreadId>>>,
+}
+
+impl Drop for ThreadBound<'_> {
+    fn drop(&mut self) {
+        self.drops.set(self.drops.get() + 1);
+        self.dropped_on.set(Some(thread::current().id()));
+    }
+}
+
+#[test]
+fn collected_scope_data_is_dropped_on_scope_thread() {
+    let _guard = TEST_MUTEX.lock();
+    let drops = Cell::new(0);
+    let dropped_on = Cell::new(None);
+
+    scope(|s| {
+        drop(s.alloc(ThreadBound {
+            drops: R::new(&drops),
+            dropped_on: R::new(&dropped_on),
+        }));
+        collect();
+        synchronize_destructors();
+        assert_eq!(drops.get(), 0);
+
+        drain_thread_local_drops();
+        assert_eq!(drops.get(), 1);
+        assert_eq!(dropped_on.get(), Some(thread::current().id()));
+    });
+    assert_eq!(drops.get(), 1);
+}
+
+#[derive(Scan)]
+struct SyncCounted<'a> {
+    drops: R<'a, AtomicUsize>,
+}
+
+impl Drop for SyncCounted<'_> {
+    fn drop(&mut self) {
+        self.drops.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn scope_waits_for_guards_on_other_threads() {
+    let _guard = TEST_MUTEX.lock();
+    let drops = AtomicUsize::new(0);
+
+    thread::scope(|t| {
+        scope(|s| {
+            let data = s.alloc(SyncCounted {
+                drops: R::new(&drops),
+            });
+            let (sender, receiver) = mpsc::channel();
+            let drops = &drops;
+            t.spawn(move || {
+                let guard = data.get();
+                sender.send(()).unwrap();
+                thread::sleep(Duration::from_millis(100));
+                // The scope has ended by now, but it can't destroy data we're still reading
+                assert_eq!(drops.load(Ordering::SeqCst), 0);
+                drop(guard);
+            });
+            receiver.recv().unwrap();
+        });
+
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    });
+}
@@ -0,0 +1,118 @@
+use std::marker::PhantomData;
+use std::sync::mpsc::channel;
+use std::sync::Mutex;
+use std::thread::{self, ThreadId};
+
+use once_cell::sync::Lazy;
+
+use shredder::*;
+
+static TEST_MUTEX: Lazy<parking_lot::Mutex<()>> = Lazy::new(|| parking_lot::Mutex::new(()));
+
+static DROPPED_ON: Lazy<Mutex<Vec<(u32, ThreadId)>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+// Stands in for something like an OpenGL context handle
+struct NotSend {
+    tag: u32,
+    _marker: PhantomData<*const ()>,
+}
+
+impl NotSend {
+    fn new(tag: u32) -> Self {
+        Self {
+            tag,
+            _marker: PhantomData,
+        }
+    }
+}
+
+// Safety: scanning is a no-op, and we only ever use `new_thread_affine`
+unsafe impl GcSafe for NotSend {}
+unsafe impl Scan for NotSend {
+    fn scan(&self, _: &mut Scanner<'_>) {}
+}
+
+impl Drop for NotSend {
+    fn drop(&mut self) {
+        DROPPED_ON
+            .lock()
+            .unwrap()
+            .push((self.tag, thread::current().id()));
+    }
+}
+
+fn dropped_on(tag: u32) -> Option<ThreadId> {
+    DROPPED_ON
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(t, _)| *t == tag)
+        .map(|(_, id)| *id)
+}
+
+#[test]
+fn drain_runs_destructors_on_owning_thread() {
+    let _guard = TEST_MUTEX.lock();
+
+    let (collected_sender, collected_receiver) = channel();
+    let (dropped_sender, dropped_receiver) = channel();
+    let owner = thread::spawn(move || {
+        drop(Gc::new_thread_affine(NotSend::new(1)));
+        dropped_sender.send(()).unwrap();
+
+        collected_receiver.recv().unwrap();
+        assert_eq!(drain_thread_local_drops(), 1);
+        thread::current().id()
+    });
+
+    dropped_receiver.recv().unwrap();
+    collect();
+    synchronize_destructors();
+    // The background thread must not have touched it
+    assert_eq!(dropped_on(1), None);
+    collected_sender.send(()).unwrap();
+
+    let owner_id = owner.join().unwrap();
+    assert_eq!(dropped_on(1), Some(owner_id));
+    assert_eq!(number_of_tracked_allocations(), 0);
+}
+
+#[test]
+fn thread_exit_runs_destructors() {
+    let _guard = TEST_MUTEX.lock();
+
+    let (collected_sender, collected_receiver) = channel();
+    let (dropped_sender, dropped_receiver) = channel();
+    let owner = thread::spawn(move || {
+        drop(Gc::new_thread_affine(NotSend::new(2)));
+        dropped_sender.send(()).unwrap();
+
+        collected_receiver.recv().unwrap();
+        thread::current().id()
+    });
+
+    dropped_receiver.recv().unwrap();
+    collect();
+    collected_sender.send(()).unwrap();
+
+    let owner_id = owner.join().unwrap();
+    assert_eq!(dropped_on(2), Some(owner_id));
+    assert_eq!(number_of_pending_destructors(), 0);
+}
+
+#[test]
+fn garbage_from_exited_thread_is_leaked() {
+    let _guard = TEST_MUTEX.lock();
+
+    thread::spawn(|| {
+        drop(Gc::new_thread_affine(NotSend::new(3)));
+    })
+    .join()
+    .unwrap();
+
+    collect();
+    synchronize_destructors();
+    assert_eq!(dropped_on(3), None);
+    assert_eq!(number_of_tracked_allocations(), 0);
+    assert_eq!(number_of_pending_destructors(), 0);
+}
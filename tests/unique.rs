@@ -0,0 +1,111 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use once_cell::sync::Lazy;
+
+use shredder::*;
+
+static TEST_MUTEX: Lazy<parking_lot::Mutex<()>> = Lazy::new(|| parking_lot::Mutex::new(()));
+
+#[derive(Scan)]
+struct Holder {
+    held: Gc<u32>,
+}
+
+#[test]
+fn handles_in_other_data_count() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let mut data = Gc::new(1);
+        let holder = Gc::new(Holder { held: data.clone() });
+        assert!(data.get_mut().is_none());
+
+        drop(holder);
+        collect();
+        synchronize_destructors();
+
+        *data.get_mut().unwrap() = 5;
+        assert_eq!(Gc::try_unwrap(data).ok(), Some(5));
+    });
+    assert_eq!(number_of_tracked_allocations(), 0);
+    assert_eq!(number_of_active_handles(), 0);
+}
+
+#[test]
+fn try_unwrap_keeps_inner_gcs_alive() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let holder = Gc::new(Holder { held: Gc::new(7) });
+        let unwrapped = Gc::try_unwrap(holder).ok().unwrap();
+        assert_eq!(number_of_tracked_allocations(), 1);
+
+        collect();
+        assert_eq!(*unwrapped.held.get(), 7);
+    });
+    assert_eq!(number_of_tracked_allocations(), 0);
+}
+
+static BOXED_DROPS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Scan)]
+struct Boxed(Vec<u32>);
+
+impl Drop for Boxed {
+    fn drop(&mut self) {
+        BOXED_DROPS.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn try_unwrap_moves_data_out_of_boxes() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let data = Gc::from_box(Box::new(Boxed(vec![1, 2, 3])));
+        let unwrapped = Gc::try_unwrap(data).ok().unwrap();
+        assert_eq!(number_of_tracked_allocations(), 0);
+        assert_eq!(BOXED_DROPS.load(Ordering::SeqCst), 0);
+        assert_eq!(unwrapped.0, vec![1, 2, 3]);
+
+        drop(unwrapped);
+        assert_eq!(BOXED_DROPS.load(Ordering::SeqCst), 1);
+    });
+}
+
+#[test]
+fn make_mut_clones_shared_data() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let mut data = Gc::new(vec![1, 2]);
+        let shared = data.clone();
+        data.make_mut().push(3);
+
+        assert_eq!(*data.get(), vec![1, 2, 3]);
+        assert_eq!(*shared.get(), vec![1, 2]);
+
+        // Now `data` is unique, so this doesn't clone
+        data.make_mut().push(4);
+        assert_eq!(number_of_tracked_allocations(), 2);
+    });
+}
+
+#[test]
+fn uniqueness_with_concurrent_collection() {
+    let _guard = TEST_MUTEX.lock();
+    run_with_gc_cleanup(|| {
+        let collector = thread::spawn(|| {
+            for _ in 0..100 {
+                collect();
+            }
+        });
+
+        for i in 0..1000 {
+            let mut data = Gc::new(Holder { held: Gc::new(i) });
+            *data.get_mut().unwrap().held.get_mut().unwrap() += 1;
+            let unwrapped = Gc::try_unwrap(data).ok().unwrap();
+            assert_eq!(*unwrapped.held.get(), i + 1);
+        }
+
+        collector.join().unwrap();
+    });
+    assert_eq!(number_of_tracked_allocations(), 0);
+}